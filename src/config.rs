@@ -0,0 +1,333 @@
+use crate::{de, process, Editor};
+use serde::de::DeserializeOwned;
+use serde_json::value::RawValue;
+use std::{error, fmt, io};
+
+/// Represents a struct whose fields are each loaded from a different part of
+/// the editor's configuration, typically implemented by
+/// `#[derive(VimConfig)]` (requires the `derive` feature)
+///
+/// ### Examples
+///
+/// ```no_run
+/// # #[cfg(feature = "derive")]
+/// # fn main() -> std::io::Result<()> {
+/// use vimvar::VimConfig;
+///
+/// #[derive(VimConfig)]
+/// struct MyPluginConfig {
+///     #[vim(global = "myplugin_width", default = 80)]
+///     width: u32,
+///
+///     #[vim(option = "shiftwidth")]
+///     shiftwidth: u32,
+///
+///     #[vim(lua = "require('myplugin').opts.theme")]
+///     theme: Option<String>,
+/// }
+///
+/// let config = MyPluginConfig::load()?;
+/// println!("Width is {}", config.width);
+/// # Ok(())
+/// # }
+/// # #[cfg(not(feature = "derive"))]
+/// # fn main() {}
+/// ```
+pub trait VimConfig: Sized {
+    /// Loads every field using a single instance of `editor`
+    ///
+    /// ### Notes
+    ///
+    /// * If any fields fail to load, the returned [`io::Error`] wraps a
+    ///   [`ConfigError`] describing each of them, which can be accessed via
+    ///   [`io::Error::get_ref`]
+    fn load_with(editor: &Editor) -> io::Result<Self>;
+
+    /// Loads every field using whatever neovim/vim instance is available in
+    /// the current path alongside the default vimrc
+    fn load() -> io::Result<Self> {
+        Self::load_with(&Editor::detect()?)
+    }
+}
+
+/// Represents where the value of a field within a [`VimConfig`] comes from
+#[doc(hidden)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ConfigSource<'a> {
+    /// Global variable with the given name (`g:myplugin_width`)
+    Global(&'a str),
+
+    /// Option with the given name (`&shiftwidth`)
+    Option(&'a str),
+
+    /// Lua expression evaluated with `luaeval()` (`require('x').opts.y`)
+    Lua(&'a str),
+}
+
+impl ConfigSource<'_> {
+    /// Returns an expression producing a List containing the value, or an
+    /// empty List if the source has not been set
+    fn to_expr(self) -> String {
+        match self {
+            Self::Global(name) => format!(
+                "has_key(g:, {0}) ? [g:[{0}]] : []",
+                process::vim_string_literal(name)
+            ),
+            Self::Option(name) => format!(
+                "[eval({})]",
+                process::vim_string_literal(&format!("&{}", name))
+            ),
+            // Evaluated once through a lambda so that nil is treated the same
+            // as an undefined global rather than converted from null
+            Self::Lua(chunk) => format!(
+                "{{x -> x is v:null ? [] : [x]}}(luaeval({}))",
+                process::vim_string_literal(chunk)
+            ),
+        }
+    }
+}
+
+impl fmt::Display for ConfigSource<'_> {
+    /// Writes source the way it would be referenced within a config, such as
+    /// `g:myplugin_width` or `&shiftwidth`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Global(name) => write!(f, "g:{}", name),
+            Self::Option(name) => write!(f, "&{}", name),
+            Self::Lua(chunk) => write!(f, "{}", chunk),
+        }
+    }
+}
+
+/// Loads the fields of a [`VimConfig`] from a single instance of the editor,
+/// collecting the error of every field that fails rather than stopping at the
+/// first, used by the code generated by `#[derive(VimConfig)]`
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct ConfigLoader<'a> {
+    fields: Vec<(ConfigSource<'a>, Result<Box<RawValue>, String>)>,
+    next: usize,
+    errors: Vec<FieldError>,
+}
+
+impl<'a> ConfigLoader<'a> {
+    /// Evaluates every source using `editor`, where the value of each is
+    /// later converted by calling [`Self::field`] in the same order
+    pub fn new(editor: &Editor, sources: &[ConfigSource<'a>]) -> io::Result<Self> {
+        let exprs: Vec<String> = sources.iter().map(|x| x.to_expr()).collect();
+        // Each value is kept as JSON until converted into the type of its field
+        let results = editor.eval_each_as(&exprs)?;
+
+        Ok(Self {
+            fields: sources.iter().copied().zip(results).collect(),
+            next: 0,
+            errors: Vec::new(),
+        })
+    }
+
+    /// Converts the value of the next source into the type of the field
+    /// named `name`, using `default` if the source has not been set
+    ///
+    /// ### Notes
+    ///
+    /// * A source that has not been set and has no default is converted from
+    ///   null, meaning `Option` fields become `None` and others fail
+    /// * Returns `None` if the field failed to load, recording the error to be
+    ///   reported by [`Self::finish`]
+    ///
+    /// ### Panics
+    ///
+    /// Panics if called more times than there are sources
+    pub fn field<T, F>(&mut self, name: &'static str, default: Option<F>) -> Option<T>
+    where
+        T: DeserializeOwned,
+        F: FnOnce() -> T,
+    {
+        let (source, result) = self
+            .fields
+            .get_mut(self.next)
+            .expect("More fields loaded than sources");
+        let source = *source;
+        let result = std::mem::replace(result, Err(String::new()));
+        self.next += 1;
+
+        let result = match result {
+            Ok(raw) => convert(source, &raw).and_then(|value| match (value, default) {
+                (Some(value), _) => Ok(value),
+                (None, Some(default)) => Ok(default()),
+                (None, None) => T::deserialize(serde_json::Value::Null).map_err(|_| {
+                    io::Error::new(io::ErrorKind::NotFound, format!("{} is not set", source))
+                }),
+            }),
+            Err(msg) => Err(io::Error::other(format!("{}: {}", source, msg))),
+        };
+
+        match result {
+            Ok(value) => Some(value),
+            Err(error) => {
+                self.errors.push(FieldError { field: name, error });
+                None
+            }
+        }
+    }
+
+    /// Completes loading, returning an [`io::Error`] wrapping a
+    /// [`ConfigError`] if any field failed to load
+    pub fn finish(self) -> io::Result<()> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                ConfigError {
+                    errors: self.errors,
+                },
+            ))
+        }
+    }
+}
+
+/// Converts `raw`, the JSON of the List produced by [`ConfigSource::to_expr`],
+/// into the value it contains, or `None` if the source has not been set
+fn convert<T>(source: ConfigSource<'_>, raw: &RawValue) -> io::Result<Option<T>>
+where
+    T: DeserializeOwned,
+{
+    let root = source.to_string();
+    let values: Vec<T> = de::from_json(
+        serde_json::de::StrRead::new(raw.get()),
+        de::Root::new(&root).within(1),
+    )
+    .map_err(|x| match x {
+        de::JsonError::Read(x) | de::JsonError::Data(x) => x,
+    })?;
+    Ok(values.into_iter().next())
+}
+
+/// Represents every field of a [`VimConfig`] that failed to load
+#[derive(Debug)]
+pub struct ConfigError {
+    errors: Vec<FieldError>,
+}
+
+impl ConfigError {
+    /// Returns the errors of each field that failed to load, in the order the
+    /// fields are declared
+    pub fn errors(&self) -> &[FieldError] {
+        &self.errors
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed to load {} field(s):", self.errors.len())?;
+        for error in &self.errors {
+            write!(f, "\n  {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for ConfigError {}
+
+/// Represents a single field of a [`VimConfig`] that failed to load
+#[derive(Debug)]
+pub struct FieldError {
+    /// Name of the field within the struct
+    pub field: &'static str,
+
+    /// Reason the field failed to load
+    pub error: io::Error,
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_should_produce_list_of_value() {
+        assert_eq!(
+            ConfigSource::Global("it's").to_expr(),
+            "has_key(g:, 'it''s') ? [g:['it''s']] : []"
+        );
+        assert_eq!(ConfigSource::Option("sw").to_expr(), "[eval('&sw')]");
+        assert_eq!(
+            ConfigSource::Lua("require('x').y").to_expr(),
+            "{x -> x is v:null ? [] : [x]}(luaeval('require(''x'').y'))"
+        );
+    }
+
+    #[test]
+    fn source_should_display_as_referenced_within_config() {
+        assert_eq!(ConfigSource::Global("foo").to_string(), "g:foo");
+        assert_eq!(ConfigSource::Option("sw").to_string(), "&sw");
+        assert_eq!(ConfigSource::Lua("vim.g.x").to_string(), "vim.g.x");
+    }
+
+    use serde_json::Value;
+
+    fn loader(
+        fields: Vec<(ConfigSource<'static>, Result<Value, String>)>,
+    ) -> ConfigLoader<'static> {
+        ConfigLoader {
+            fields: fields
+                .into_iter()
+                .map(|(source, result)| {
+                    let result = result.map(|x| serde_json::value::to_raw_value(&x).unwrap());
+                    (source, result)
+                })
+                .collect(),
+            next: 0,
+            errors: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn loader_should_use_default_if_source_is_not_set() {
+        let mut loader = loader(vec![
+            (ConfigSource::Global("a"), Ok(serde_json::json!([]))),
+            (ConfigSource::Global("b"), Ok(serde_json::json!([]))),
+            (ConfigSource::Global("c"), Ok(serde_json::json!([5]))),
+        ]);
+
+        assert_eq!(loader.field("a", Some(|| 80u32)), Some(80));
+        assert_eq!(loader.field("b", None::<fn() -> Option<u32>>), Some(None));
+        assert_eq!(loader.field("c", Some(|| 80u32)), Some(5));
+        assert!(loader.finish().is_ok());
+    }
+
+    #[test]
+    fn loader_should_report_every_field_that_failed() {
+        let mut loader = loader(vec![
+            (ConfigSource::Global("a"), Ok(serde_json::json!([]))),
+            (ConfigSource::Option("b"), Ok(serde_json::json!(["text"]))),
+            (ConfigSource::Global("c"), Ok(serde_json::json!([1]))),
+            (ConfigSource::Lua("d"), Err("E5108: boom".to_string())),
+        ]);
+
+        assert_eq!(loader.field::<u32, fn() -> u32>("a", None), None);
+        assert_eq!(loader.field::<u32, fn() -> u32>("b", None), None);
+        assert_eq!(loader.field::<u32, fn() -> u32>("c", None), Some(1));
+        assert_eq!(loader.field::<u32, fn() -> u32>("d", None), None);
+
+        let err = loader.finish().unwrap_err();
+        let err = err
+            .get_ref()
+            .and_then(|x| x.downcast_ref::<ConfigError>())
+            .expect("Missing config error");
+        let fields: Vec<&str> = err.errors().iter().map(|x| x.field).collect();
+        assert_eq!(fields, vec!["a", "b", "d"]);
+
+        let msg = err.to_string();
+        assert!(msg.starts_with("Failed to load 3 field(s):"), "{}", msg);
+        assert!(msg.contains("\n  a: g:a is not set"), "{}", msg);
+        assert!(msg.contains("\n  b: &b: invalid type"), "{}", msg);
+        assert!(msg.contains("\n  d: d: E5108: boom"), "{}", msg);
+    }
+}
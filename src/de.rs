@@ -0,0 +1,547 @@
+use crate::{process, VimValue};
+use serde::de::{
+    self,
+    value::{Error, MapDeserializer, SeqDeserializer},
+    Deserialize, DeserializeSeed, Deserializer, EnumAccess, IntoDeserializer, Unexpected,
+    VariantAccess, Visitor,
+};
+use serde_path_to_error::{Path, Segment};
+use std::{collections::BTreeMap, fmt, io};
+
+/// Represents the expression that produced a value being deserialized, used
+/// to report the path of a value that fails to convert relative to it
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Root<'a> {
+    /// Expression (such as `g:myplugin`) or prefix of variable names (such as
+    /// `g:ale_`) that paths are relative to
+    expr: &'a str,
+
+    /// Whether `expr` is a prefix that was removed from the first key of the
+    /// path, which is appended directly to it
+    prefixed: bool,
+
+    /// Number of segments at the start of a path that belong to types
+    /// wrapping the value (such as `{"ok": value}`) and are not reported
+    envelope: usize,
+}
+
+impl<'a> Root<'a> {
+    /// Creates a root for values produced by `expr`
+    pub(crate) fn new(expr: &'a str) -> Self {
+        Self {
+            expr,
+            prefixed: false,
+            envelope: 0,
+        }
+    }
+
+    /// Creates a root for a Dict of variables whose names had `prefix` (such
+    /// as `g:ale_`) removed, reporting failures using the full name of the
+    /// variable (such as `g:ale_linters.rust[0]`)
+    pub(crate) fn prefixed(prefix: &'a str) -> Self {
+        Self {
+            expr: prefix,
+            prefixed: true,
+            envelope: 0,
+        }
+    }
+
+    /// Returns the same root for a value nested within `count` more layers
+    /// of wrapping types, each contributing a single path segment
+    pub(crate) fn within(self, count: usize) -> Self {
+        Self {
+            envelope: self.envelope + count,
+            ..self
+        }
+    }
+
+    /// Creates an [`io::Error`] describing `error`, prefixed by the path of
+    /// the value that failed unless the path is empty
+    fn error(&self, path: &Path, error: impl fmt::Display) -> io::Error {
+        let path = self.format_path(path);
+        let msg = if path.is_empty() {
+            error.to_string()
+        } else {
+            format!("{}: {}", path, error)
+        };
+        io::Error::new(io::ErrorKind::InvalidData, msg)
+    }
+
+    /// Formats `path` as a Vimscript expression relative to the root, where
+    /// keys that are not valid identifiers use brackets
+    /// (`g:foo['key with spaces']`)
+    fn format_path(&self, path: &Path) -> String {
+        let mut out = if self.expr.is_empty() || is_simple_expr(self.expr) {
+            self.expr.to_string()
+        } else {
+            format!("({})", self.expr)
+        };
+
+        let mut segments = path.iter().skip(self.envelope).peekable();
+        if self.prefixed {
+            if let Some(Segment::Map { key }) = segments.peek() {
+                out.push_str(key);
+                segments.next();
+            }
+        }
+
+        for segment in segments {
+            match segment {
+                Segment::Seq { index } => out.push_str(&format!("[{}]", index)),
+                Segment::Map { key } | Segment::Enum { variant: key } if is_identifier(key) => {
+                    if !out.is_empty() {
+                        out.push('.');
+                    }
+                    out.push_str(key);
+                }
+                Segment::Map { key } | Segment::Enum { variant: key } => {
+                    out.push_str(&format!("[{}]", process::vim_string_literal(key)));
+                }
+                Segment::Unknown => out.push_str("[?]"),
+            }
+        }
+
+        out
+    }
+}
+
+/// Deserializes `T` using `deserializer`, reporting failures as an
+/// [`io::Error`] that includes the path (starting at `root`) of the value that
+/// failed, such as `g:myplugin.servers[3].port: expected u16`
+pub(crate) fn deserialize<'de, D, T>(deserializer: D, root: &str) -> io::Result<T>
+where
+    D: Deserializer<'de>,
+    D::Error: fmt::Display,
+    T: Deserialize<'de>,
+{
+    let root = Root::new(root);
+    serde_path_to_error::deserialize(deserializer).map_err(|x| root.error(x.path(), x.inner()))
+}
+
+/// Represents a failure to deserialize the JSON read by [`from_json`]
+#[derive(Debug)]
+pub(crate) enum JsonError {
+    /// JSON could not be read or is malformed
+    Read(io::Error),
+
+    /// JSON is valid, but does not match the type being deserialized, where
+    /// the error includes the path of the value that failed
+    Data(io::Error),
+}
+
+/// Deserializes `T` directly from the JSON provided by `read` rather than
+/// decoding it into a [`serde_json::Value`] first, reporting values that fail
+/// to convert the same way as [`deserialize`]
+pub(crate) fn from_json<'de, R, T>(read: R, root: Root<'_>) -> Result<T, JsonError>
+where
+    R: serde_json::de::Read<'de>,
+    T: Deserialize<'de>,
+{
+    let mut deserializer = serde_json::Deserializer::new(read);
+    let value = serde_path_to_error::deserialize(&mut deserializer).map_err(|x| {
+        if x.inner().is_data() {
+            JsonError::Data(root.error(x.path(), x.inner()))
+        } else {
+            JsonError::Read(malformed(x.inner()))
+        }
+    })?;
+
+    deserializer
+        .end()
+        .map_err(|x| JsonError::Read(malformed(&x)))?;
+    Ok(value)
+}
+
+/// Creates an [`io::Error`] reporting that JSON could not be parsed
+fn malformed(error: &serde_json::Error) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("failed to parse as JSON: {}", error),
+    )
+}
+
+/// Returns true if `s` can be used as a key with Vimscript's `dict.key` syntax
+fn is_identifier(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Returns true if `s` is an expression (such as `g:foo['a b'][0]` or `&tw`)
+/// that can be indexed without being wrapped in parentheses
+fn is_simple_expr(s: &str) -> bool {
+    let mut quoted = false;
+    s.chars().all(|c| {
+        if c == '\'' {
+            quoted = !quoted;
+        }
+        quoted || c.is_ascii_alphanumeric() || "_:#&.[]'".contains(c)
+    })
+}
+
+impl VimValue {
+    /// Describes the value for use within deserialization errors
+    fn unexpected(&self) -> Unexpected<'_> {
+        match self {
+            Self::Number(x) => Unexpected::Signed(*x),
+            Self::Float(x) => Unexpected::Float(*x),
+            Self::String(x) => Unexpected::Str(x),
+            Self::List(_) => Unexpected::Seq,
+            Self::Dict(_) => Unexpected::Map,
+            Self::Blob(x) => Unexpected::Bytes(x),
+            Self::Bool(x) => Unexpected::Bool(*x),
+            Self::Null | Self::None => Unexpected::Unit,
+            Self::Funcref(_) | Self::Special { .. } => Unexpected::Other(self.kind().as_str()),
+        }
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for VimValue {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+/// Deserializes directly from a [`VimValue`], following the same conversions
+/// as [`VimValue::into_typed`]
+impl<'de> Deserializer<'de> for VimValue {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Self::Number(x) => visitor.visit_i64(x),
+            Self::Float(x) => visitor.visit_f64(x),
+            Self::String(x) | Self::Funcref(x) | Self::Special { repr: x, .. } => {
+                visitor.visit_string(x)
+            }
+            Self::List(x) => visit_seq(x, visitor),
+            Self::Dict(x) => visit_map(x, visitor),
+            Self::Blob(x) => visit_seq(x, visitor),
+            Self::Bool(x) => visitor.visit_bool(x),
+            Self::Null | Self::None => visitor.visit_unit(),
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Self::Null | Self::None => visitor.visit_none(),
+            x => visitor.visit_some(x),
+        }
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Self::Blob(x) => visitor.visit_byte_buf(x),
+            x => x.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Self::String(variant) => visitor.visit_enum(EnumDeserializer {
+                variant,
+                value: Self::Null,
+            }),
+            Self::Dict(x) if x.len() == 1 => {
+                let (variant, value) = x.into_iter().next().unwrap();
+                visitor.visit_enum(EnumDeserializer { variant, value })
+            }
+            x => Err(de::Error::invalid_type(
+                x.unexpected(),
+                &"string or dict with a single key",
+            )),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+fn visit_seq<'de, I, V>(items: Vec<I>, visitor: V) -> Result<V::Value, Error>
+where
+    I: IntoDeserializer<'de, Error>,
+    V: Visitor<'de>,
+{
+    let mut seq = SeqDeserializer::new(items.into_iter());
+    let value = visitor.visit_seq(&mut seq)?;
+    seq.end()?;
+    Ok(value)
+}
+
+fn visit_map<'de, V>(items: BTreeMap<String, VimValue>, visitor: V) -> Result<V::Value, Error>
+where
+    V: Visitor<'de>,
+{
+    let mut map = MapDeserializer::new(items.into_iter());
+    let value = visitor.visit_map(&mut map)?;
+    map.end()?;
+    Ok(value)
+}
+
+/// Externally tagged enum, either a String naming a unit variant or a Dict
+/// with a single key naming the variant
+struct EnumDeserializer {
+    variant: String,
+    value: VimValue,
+}
+
+impl<'de> EnumAccess<'de> for EnumDeserializer {
+    type Error = Error;
+    type Variant = VariantDeserializer;
+
+    fn variant_seed<T>(self, seed: T) -> Result<(T::Value, Self::Variant), Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        let variant =
+            seed.deserialize(IntoDeserializer::<Error>::into_deserializer(self.variant))?;
+        Ok((variant, VariantDeserializer(self.value)))
+    }
+}
+
+struct VariantDeserializer(VimValue);
+
+impl<'de> VariantAccess<'de> for VariantDeserializer {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        match self.0 {
+            VimValue::Null | VimValue::None => Ok(()),
+            x => Err(de::Error::invalid_type(x.unexpected(), &"unit variant")),
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        seed.deserialize(self.0)
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.0.deserialize_seq(visitor)
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.0.deserialize_map(visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Server {
+        port: u16,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Config {
+        servers: Vec<Server>,
+    }
+
+    fn error_message<T: std::fmt::Debug>(result: io::Result<T>) -> String {
+        let err = result.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        err.to_string()
+    }
+
+    #[test]
+    fn deserialize_should_include_path_of_failing_value() {
+        let value = json!({ "servers": [{ "port": 80 }, { "port": "http" }] });
+        let msg = error_message(deserialize::<_, Config>(value, "g:myplugin"));
+
+        assert!(
+            msg.starts_with("g:myplugin.servers[1].port: invalid type: string \"http\""),
+            "Unexpected message: {}",
+            msg
+        );
+        assert!(msg.ends_with("expected u16"), "Unexpected message: {}", msg);
+    }
+
+    #[test]
+    fn deserialize_should_use_brackets_for_keys_that_are_not_identifiers() {
+        let value = json!({ "key with spaces": "text" });
+        let msg = error_message(deserialize::<_, BTreeMap<String, u8>>(value, "g:foo"));
+
+        assert!(
+            msg.starts_with("g:foo['key with spaces']: "),
+            "Unexpected message: {}",
+            msg
+        );
+    }
+
+    #[test]
+    fn deserialize_should_wrap_complex_roots_in_parentheses() {
+        let msg = error_message(deserialize::<_, Vec<u8>>(json!([1, -1]), "range(1) + [-1]"));
+
+        assert!(
+            msg.starts_with("(range(1) + [-1])[1]: "),
+            "Unexpected message: {}",
+            msg
+        );
+    }
+
+    #[test]
+    fn from_json_should_append_first_key_to_prefix() {
+        let read = serde_json::de::StrRead::new(r#"{ "linters": { "rust": [1, "cargo"] } }"#);
+        let msg = match from_json::<_, BTreeMap<String, BTreeMap<String, Vec<u8>>>>(
+            read,
+            Root::prefixed("g:ale_"),
+        ) {
+            Err(JsonError::Data(err)) => err.to_string(),
+            x => panic!("Unexpected result: {:?}", x.map(|_| ())),
+        };
+
+        assert!(
+            msg.starts_with("g:ale_linters.rust[1]: "),
+            "Unexpected message: {}",
+            msg
+        );
+    }
+
+    #[test]
+    fn deserialize_should_omit_empty_path() {
+        let msg = error_message(deserialize::<_, u8>(json!("text"), ""));
+        assert!(
+            msg.starts_with("invalid type"),
+            "Unexpected message: {}",
+            msg
+        );
+    }
+
+    #[test]
+    fn from_json_should_skip_envelope_within_path() {
+        let read = serde_json::de::StrRead::new(r#"{"ok": {"servers": [{"port": -1}]}}"#);
+        let err =
+            match from_json::<_, BTreeMap<String, Config>>(read, Root::new("g:myplugin").within(1))
+            {
+                Err(JsonError::Data(err)) => err,
+                x => panic!("Unexpected result: {:?}", x.map(|_| ())),
+            };
+
+        let msg = err.to_string();
+        assert!(
+            msg.starts_with("g:myplugin.servers[0].port: "),
+            "Unexpected message: {}",
+            msg
+        );
+    }
+
+    #[test]
+    fn from_json_should_fail_if_json_is_malformed() {
+        let read = serde_json::de::StrRead::new(r#"{"ok": [1, 2"#);
+        match from_json::<_, BTreeMap<String, Vec<u8>>>(read, Root::new("")) {
+            Err(JsonError::Read(err)) => assert!(
+                err.to_string().starts_with("failed to parse as JSON: "),
+                "Unexpected message: {}",
+                err
+            ),
+            x => panic!("Unexpected result: {:?}", x.map(|_| ())),
+        }
+
+        let read = serde_json::de::StrRead::new("[1] [2]");
+        assert!(matches!(
+            from_json::<_, Vec<u8>>(read, Root::new("")),
+            Err(JsonError::Read(_))
+        ));
+    }
+
+    #[test]
+    fn vim_value_should_deserialize_enums() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        enum Mode {
+            Off,
+            Limit(u8),
+            Range { min: u8, max: u8 },
+        }
+
+        let value = VimValue::List(vec![
+            VimValue::String("Off".into()),
+            VimValue::Dict(
+                vec![("Limit".to_string(), VimValue::Number(3))]
+                    .into_iter()
+                    .collect(),
+            ),
+            VimValue::Dict(
+                vec![(
+                    "Range".to_string(),
+                    VimValue::Dict(
+                        vec![
+                            ("min".to_string(), VimValue::Number(1)),
+                            ("max".to_string(), VimValue::Number(2)),
+                        ]
+                        .into_iter()
+                        .collect(),
+                    ),
+                )]
+                .into_iter()
+                .collect(),
+            ),
+        ]);
+
+        let modes: Vec<Mode> = deserialize(value, "").unwrap();
+        assert_eq!(
+            modes,
+            vec![Mode::Off, Mode::Limit(3), Mode::Range { min: 1, max: 2 }]
+        );
+    }
+
+    #[test]
+    fn vim_value_should_deserialize_non_finite_floats() {
+        let value: f64 = deserialize(VimValue::Float(f64::INFINITY), "").unwrap();
+        assert_eq!(value, f64::INFINITY);
+    }
+}
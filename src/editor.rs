@@ -0,0 +1,959 @@
+use crate::{
+    de,
+    highlight::{Palette, RawHighlightedText, RawPalette, StyledSpan},
+    literal::{self, FunctionArgs},
+    options::{CommaList, OptionInfo, OptionScope, OptionValue, RawOption, RawOptionInfo},
+    process, provenance, search, utils, Cmd, Provenance, RawString, Scope, VarInfo, VimType,
+    VimValue,
+};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    convert::TryFrom,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// Represents the result of evaluating something within the editor alongside
+/// anything else the editor printed while doing so
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Evaluated<T> {
+    /// The evaluated value
+    pub value: T,
+
+    /// Non-blank lines printed by the editor outside of the value, such as
+    /// messages echoed by plugins during startup
+    pub diagnostics: Vec<String>,
+}
+
+/// Represents the settings in effect for a file once the editor has opened it,
+/// after filetype detection, ftplugins, modelines and other autocommands
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileSettings {
+    /// Detected filetype, or an empty string if none was detected
+    pub filetype: String,
+
+    /// Effective value of every buffer-local and window-local option (such
+    /// as `shiftwidth` or `conceallevel`) keyed by full name
+    pub options: BTreeMap<String, OptionValue>,
+
+    /// Variables within the buffer's `b:` scope (such as `undo_ftplugin`)
+    /// keyed by name without the scope
+    pub vars: BTreeMap<String, VimValue>,
+}
+
+impl FileSettings {
+    /// Returns the value of the option `name`, which must be a full name
+    /// (such as `shiftwidth` rather than `sw`)
+    pub fn option(&self, name: &str) -> Option<&OptionValue> {
+        self.options.get(name)
+    }
+
+    /// Returns the value of the buffer variable `name` (without `b:`)
+    pub fn var(&self, name: &str) -> Option<&VimValue> {
+        self.vars.get(name)
+    }
+}
+
+/// Represents a command used by [`Editor::format`] to change the text of a
+/// buffer
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FormatCommand<'a> {
+    /// Reindent every line (`gg=G`)
+    Indent,
+
+    /// Format every line using `'formatexpr'`, `'formatprg'` or
+    /// `'textwidth'` (`gggqG`)
+    Format,
+
+    /// Normal mode command (such as `gg=G` or `<leader>f`), where mappings
+    /// are used the same way as `:normal` and special keys such as `<CR>`
+    /// are written in their `<>` form
+    Normal(&'a str),
+
+    /// Ex command (such as `%s/\s\+$//e`) without the leading `:`
+    Ex(&'a str),
+}
+
+impl FormatCommand<'_> {
+    /// Converts into the Ex command to run
+    ///
+    /// ### Examples
+    ///
+    /// ```
+    /// use vimvar::FormatCommand;
+    ///
+    /// assert_eq!(FormatCommand::Indent.to_ex(), "normal! gg=G");
+    /// assert_eq!(FormatCommand::Format.to_ex(), "normal! gggqG");
+    /// assert_eq!(
+    ///     FormatCommand::Normal("gg<leader>f").to_ex(),
+    ///     "execute \"normal gg\\<leader>f\"",
+    /// );
+    /// assert_eq!(FormatCommand::Ex("retab").to_ex(), "retab");
+    /// ```
+    pub fn to_ex(&self) -> String {
+        match self {
+            Self::Indent => String::from("normal! gg=G"),
+            Self::Format => String::from("normal! gggqG"),
+            Self::Normal(keys) => {
+                // Special keys are only expanded within double-quoted strings
+                let mut escaped = String::new();
+                for c in keys.chars() {
+                    match c {
+                        '<' => escaped.push_str("\\<"),
+                        '"' | '\\' => {
+                            escaped.push('\\');
+                            escaped.push(c);
+                        }
+                        c => escaped.push(c),
+                    }
+                }
+                format!("execute \"normal {}\"", escaped)
+            }
+            Self::Ex(cmd) => cmd.to_string(),
+        }
+    }
+}
+
+/// Default maximum size (in bytes) of a result produced by the editor, used to
+/// protect against accidentally loading enormous values
+pub const DEFAULT_MAX_OUTPUT_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Represents a neovim/vim instance paired with the config it loads, used to
+/// query the editor's state after the config has been sourced
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Editor {
+    cmd: Cmd,
+    config: PathBuf,
+    max_output_size: Option<u64>,
+}
+
+impl Editor {
+    /// Creates a new editor definition that will load `config` when queried
+    pub fn new(cmd: Cmd, config: impl Into<PathBuf>) -> Self {
+        Self {
+            cmd,
+            config: config.into(),
+            max_output_size: Some(DEFAULT_MAX_OUTPUT_SIZE),
+        }
+    }
+
+    /// Sets the maximum size (in bytes) of a result produced by the editor,
+    /// where `None` means there is no limit, defaulting to
+    /// [`DEFAULT_MAX_OUTPUT_SIZE`]
+    ///
+    /// Results larger than this are reported as an [`io::Error`] rather than
+    /// being loaded.
+    pub fn with_max_output_size(self, max_output_size: Option<u64>) -> Self {
+        Self {
+            max_output_size,
+            ..self
+        }
+    }
+
+    /// Creates a new editor definition using whatever neovim/vim instance is
+    /// available in the current path alongside the default vimrc
+    ///
+    /// ### Notes
+    ///
+    /// * Will leverage [`utils::find_cmd`] to determine the instance
+    /// * Will leverage [`search::find_vimrc`] to determine the vimrc
+    pub fn detect() -> io::Result<Self> {
+        let cmd = utils::find_cmd()?;
+        let config = search::find_vimrc()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "vimrc not found"))?;
+        Ok(Self::new(cmd, config))
+    }
+
+    /// Returns [`Cmd`] tied to editor
+    pub fn cmd(&self) -> Cmd {
+        self.cmd
+    }
+
+    /// Returns path to the config tied to editor
+    pub fn config(&self) -> &Path {
+        self.config.as_path()
+    }
+
+    /// Returns maximum size (in bytes) of a result produced by the editor
+    pub fn max_output_size(&self) -> Option<u64> {
+        self.max_output_size
+    }
+
+    /// Evaluates the Vimscript expression `expr` (such as `&shiftwidth`,
+    /// `exists('*SomeFunc')` or `g:foo.bar[0]`) after the config is sourced,
+    /// converting the result to the specified type
+    ///
+    /// ### Notes
+    ///
+    /// * The expression is handed to the editor as-is and evaluated with
+    ///   `eval()`, so quotes within it need no escaping
+    /// * Returns an [`io::Error`] if the expression throws (for instance, if
+    ///   it references an undefined variable) or the result fails to convert,
+    ///   where conversion errors include the path of the value that failed
+    ///   (such as `g:myplugin.servers[3].port: invalid type: ..., expected u16`)
+    pub fn eval<T>(&self, expr: &str) -> io::Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        Ok(self.eval_with_diagnostics(expr)?.value)
+    }
+
+    /// Same as [`Self::eval`], but also returns anything else printed by the
+    /// editor (such as messages echoed by plugins during startup)
+    pub fn eval_with_diagnostics<T>(&self, expr: &str) -> io::Result<Evaluated<T>>
+    where
+        T: DeserializeOwned,
+    {
+        process::eval_json(self, expr, de::Root::new(expr))
+    }
+
+    /// Same as [`Self::eval`], but starts vim as if within a 256 color
+    /// terminal, used when loading highlight groups
+    fn eval_with_colors<T>(&self, expr: &str) -> io::Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        Ok(process::eval_json_with_colors(self, expr, de::Root::new(expr))?.value)
+    }
+
+    /// Evaluates the Vimscript expression `expr`, which must result in either
+    /// an empty List or a List containing a single value, converting the
+    /// value to the specified type where conversion errors report paths
+    /// relative to `root` (such as `g:myplugin`) rather than `expr`
+    pub(crate) fn eval_found<T>(&self, expr: &str, root: &str) -> io::Result<Option<T>>
+    where
+        T: DeserializeOwned,
+    {
+        let found: Vec<T> = process::eval_json(self, expr, de::Root::new(root).within(1))?.value;
+        Ok(found.into_iter().next())
+    }
+
+    /// Evaluates the Vimscript expression `expr` after the config is sourced,
+    /// returning the result as a [`VimValue`] that preserves Vimscript types
+    ///
+    /// ### Notes
+    ///
+    /// * The editor reports the `type()` of the result and every value nested
+    ///   within it, so Numbers and Floats, Booleans and Numbers, Blobs,
+    ///   Funcrefs and other special values can be told apart
+    pub fn eval_value(&self, expr: &str) -> io::Result<VimValue> {
+        let tagged = self.eval(&format!("s:tagged({})", expr))?;
+        VimValue::from_tagged(tagged)
+    }
+
+    /// Evaluates the Vimscript expression `expr`, which must result in a String
+    /// or Blob, returning its exact bytes alongside the editor's `'encoding'`
+    ///
+    /// ### Notes
+    ///
+    /// * The bytes are transferred as a List of numbers, meaning strings that
+    ///   are not valid UTF-8 (such as latin1 text or binary data) are kept
+    ///   exactly rather than being reinterpreted
+    pub fn eval_raw(&self, expr: &str) -> io::Result<RawString> {
+        self.eval(&format!(
+            "{{'bytes': s:bytes({}), 'encoding': &encoding}}",
+            expr
+        ))
+    }
+
+    /// Same as [`Self::eval_raw`], but only returns the bytes
+    pub fn eval_bytes(&self, expr: &str) -> io::Result<Vec<u8>> {
+        Ok(self.eval_raw(expr)?.into_bytes())
+    }
+
+    /// Evaluates each Vimscript expression within `exprs` independently using
+    /// a single instance of the editor, returning the result of each in the
+    /// same order where an expression that throws results in the exception
+    /// rather than failing the others
+    ///
+    /// ### Notes
+    ///
+    /// * Returns an [`io::Error`] only if the editor itself fails, such as
+    ///   when it cannot be spawned
+    pub fn eval_each<S>(&self, exprs: &[S]) -> io::Result<Vec<Result<Value, String>>>
+    where
+        S: AsRef<str>,
+    {
+        self.eval_each_as(exprs)
+    }
+
+    /// Same as [`Self::eval_each`], but converts the result of every
+    /// expression to the specified type
+    pub(crate) fn eval_each_as<S, T>(&self, exprs: &[S]) -> io::Result<Vec<Result<T, String>>>
+    where
+        S: AsRef<str>,
+        T: DeserializeOwned,
+    {
+        let exprs: Vec<&str> = exprs.iter().map(AsRef::as_ref).collect();
+        let exprs = literal::to_string(&exprs)
+            .map_err(|x| io::Error::new(io::ErrorKind::InvalidInput, x))?;
+        let outcomes: Vec<process::Outcome<T>> = self.eval(&format!("s:eval_each({})", exprs))?;
+
+        Ok(outcomes
+            .into_iter()
+            .map(|outcome| match outcome {
+                process::Outcome::Ok(value) => Ok(value),
+                process::Outcome::Err(msg) => Err(msg),
+            })
+            .collect())
+    }
+
+    /// Calls the function named `func` (such as `myplugin#config#resolve`)
+    /// after the config is sourced, converting its return value to the
+    /// specified type
+    ///
+    /// ### Notes
+    ///
+    /// * `args` is a tuple where each element is a separate argument converted
+    ///   into a Vimscript literal using [`literal::to_string`], meaning a
+    ///   single argument is passed as `(value,)` and `()` calls the function
+    ///   without arguments
+    /// * The function is invoked via `call()`, meaning autoload functions are
+    ///   loaded as needed
+    pub fn call<R>(&self, func: &str, args: impl FunctionArgs) -> io::Result<R>
+    where
+        R: DeserializeOwned,
+    {
+        let args = args
+            .to_list()
+            .map_err(|x| io::Error::new(io::ErrorKind::InvalidInput, x))?;
+
+        self.eval(&format!(
+            "call({}, {})",
+            process::vim_string_literal(func),
+            args
+        ))
+    }
+
+    /// Runs the Ex command `cmd` (such as `scriptnames`, `map` or
+    /// `verbose set tw?`) after the config is sourced, returning each line of
+    /// the text it prints
+    ///
+    /// ### Notes
+    ///
+    /// * Output is captured using `execute()`, so only the text printed by
+    ///   `cmd` itself is returned
+    /// * Blank lines at the start and end of the output (such as the newline
+    ///   that begins every message) are removed
+    pub fn capture(&self, cmd: &str) -> io::Result<Vec<String>> {
+        let lines: Vec<String> = self.eval(&format!(
+            "split(execute({}), \"\\n\", 1)",
+            process::vim_string_literal(cmd)
+        ))?;
+
+        let start = lines
+            .iter()
+            .position(|line| !line.trim().is_empty())
+            .unwrap_or(lines.len());
+        let end = lines
+            .iter()
+            .rposition(|line| !line.trim().is_empty())
+            .map_or(start, |i| i + 1);
+        Ok(lines[start..end].to_vec())
+    }
+
+    /// Runs the Lua `chunk` (such as `require('myplugin').config` or
+    /// `return vim.g.some_table`) after the config is sourced, converting its
+    /// return value to the specified type
+    ///
+    /// ### Notes
+    ///
+    /// * Only supported by neovim
+    /// * The chunk may either be a single expression or a series of
+    ///   statements ending in `return`
+    /// * The value is encoded with `vim.json.encode`, where `nil` and
+    ///   `vim.NIL` become null
+    /// * Returns an [`io::Error`] if the chunk raises an error or returns a
+    ///   table containing functions or userdata, which cannot be encoded
+    pub fn eval_lua<T>(&self, chunk: &str) -> io::Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        Ok(self.eval_lua_with_diagnostics(chunk)?.value)
+    }
+
+    /// Same as [`Self::eval_lua`], but also returns anything else printed by
+    /// the editor (such as messages echoed by plugins during startup)
+    pub fn eval_lua_with_diagnostics<T>(&self, chunk: &str) -> io::Result<Evaluated<T>>
+    where
+        T: DeserializeOwned,
+    {
+        process::eval_lua_json(self, chunk)
+    }
+
+    /// Lists the variables defined within `scope` (such as `g:`) after the
+    /// config is sourced, optionally limited to names starting with `prefix`,
+    /// returning each variable's name and type ordered by name
+    ///
+    /// ### Notes
+    ///
+    /// * Only scopes that exist outside of functions and scripts
+    ///   ([`Scope::Global`], [`Scope::Buffer`], [`Scope::Window`],
+    ///   [`Scope::Tabpage`] and [`Scope::Vim`]) can be listed, and an
+    ///   [`io::Error`] is returned for any other scope
+    /// * Values are not loaded, see [`Self::list_vars_with_values`]
+    pub fn list_vars(&self, scope: Scope, prefix: Option<&str>) -> io::Result<Vec<VarInfo>> {
+        let types: BTreeMap<String, i64> = self.eval(&format!(
+            "map({}, {{_, v -> type(v)}})",
+            vars_expr(scope, prefix)?
+        ))?;
+
+        Ok(types
+            .into_iter()
+            .map(|(name, t)| VarInfo {
+                name,
+                kind: VimType::from_type_number(t),
+                value: None,
+            })
+            .collect())
+    }
+
+    /// Same as [`Self::list_vars`], but also loads the value of each variable
+    pub fn list_vars_with_values(
+        &self,
+        scope: Scope,
+        prefix: Option<&str>,
+    ) -> io::Result<Vec<VarInfo>> {
+        let vars = into_vars(self.eval_value(&vars_expr(scope, prefix)?)?)?;
+
+        Ok(vars
+            .into_iter()
+            .map(|(name, value)| VarInfo {
+                name,
+                kind: value.kind(),
+                value: Some(value),
+            })
+            .collect())
+    }
+
+    /// Loads every global variable whose name starts with `prefix` (such as
+    /// `g:ale_linters` and `g:ale_fixers` for the prefix `ale_`) after the
+    /// config is sourced, converting them into the specified type as a single
+    /// Dict keyed by the names with `prefix` removed
+    ///
+    /// ### Notes
+    ///
+    /// * All variables are loaded using a single instance of the editor
+    /// * Variables the user has not set are simply absent, so fields of `T`
+    ///   can use serde's `#[serde(default)]` to fill them in
+    /// * Conversion errors name the full variable, such as
+    ///   `g:ale_linters.rust[0]`
+    pub fn load_prefixed<T>(&self, prefix: &str) -> io::Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        let expr = format!(
+            "s:strip_prefix({}, {})",
+            vars_expr(Scope::Global, Some(prefix))?,
+            process::vim_string_literal(prefix)
+        );
+        let root = format!("g:{}", prefix);
+
+        Ok(process::eval_json(self, &expr, de::Root::prefixed(&root))?.value)
+    }
+
+    /// Loads the value of the option `name` (such as `shiftwidth` or `sw`) in
+    /// effect for the current buffer and window after the config is sourced,
+    /// typed as a boolean, number or string
+    pub fn option(&self, name: &str) -> io::Result<OptionValue> {
+        self.option_with_scope(name, OptionScope::Effective)
+    }
+
+    /// Same as [`Self::option`], but loads the value from `scope`, allowing the
+    /// local and global values of an option to be told apart
+    ///
+    /// ### Notes
+    ///
+    /// * Options that are global but can also have a local value (such as
+    ///   `'undolevels'`) report the local value as an empty string or a
+    ///   special number (-123456) when it has not been set, matching `&l:`
+    /// * Returns an [`io::Error`] if the option does not exist
+    pub fn option_with_scope(&self, name: &str, scope: OptionScope) -> io::Result<OptionValue> {
+        let raw: RawOption = self.eval(&format!(
+            "s:option({}, {})",
+            process::vim_string_literal(name),
+            process::vim_string_literal(scope.as_str())
+        ))?;
+        raw.into_value()
+    }
+
+    /// Lists every option supported by the editor alongside its value in
+    /// effect for the current buffer and window, ordered by name
+    ///
+    /// ### Notes
+    ///
+    /// * Uses `nvim_get_all_options_info()` for neovim, which also provides
+    ///   metadata such as each option's default and whether it is local to
+    ///   buffers or windows
+    /// * Uses `getcompletion('', 'option')` for vim, which only provides the
+    ///   name, type and value of each option
+    pub fn list_options(&self) -> io::Result<Vec<OptionInfo>> {
+        let raw: Vec<RawOptionInfo> = self.eval("s:options()")?;
+        let mut options = raw
+            .into_iter()
+            .map(OptionInfo::try_from)
+            .collect::<io::Result<Vec<_>>>()?;
+
+        options.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(options)
+    }
+
+    /// Same as [`Self::option`], but also returns the script and line that
+    /// last set the option, as reported by `:verbose set name?`
+    ///
+    /// ### Notes
+    ///
+    /// * Options that have not been changed since startup have no file or line
+    /// * Returns an [`io::Error`] if the option does not exist
+    pub fn option_provenance(&self, name: &str) -> io::Result<Provenance<OptionValue>> {
+        let result: Option<(RawOption, Vec<String>)> = self.eval(&format!(
+            "exists('+' . {0}) ? [s:option({0}, '&'), s:verbose('set ' . {0} . '?')] : v:null",
+            process::vim_string_literal(name)
+        ))?;
+        let (raw, lines) = result.ok_or_else(|| not_found("Option", name))?;
+
+        Ok(Provenance::new(
+            raw.into_value()?,
+            provenance::find_last_set(&lines),
+        ))
+    }
+
+    /// Loads the definition of the user function `name` (such as
+    /// `MyFunction` or `myplugin#run`) as printed by `:verbose function`,
+    /// alongside the script and line that defined it
+    ///
+    /// ### Notes
+    ///
+    /// * Returns an [`io::Error`] if the function does not exist
+    /// * Lua functions cannot be listed this way
+    pub fn function_provenance(&self, name: &str) -> io::Result<Provenance<Vec<String>>> {
+        let lines: Vec<String> = self
+            .eval::<Option<_>>(&format!(
+                "exists('*' . {0}) ? s:verbose('function ' . {0}) : v:null",
+                process::vim_string_literal(name)
+            ))?
+            .ok_or_else(|| not_found("Function", name))?;
+
+        let last_set = provenance::find_last_set(&lines).map(String::from);
+        let definition = lines
+            .into_iter()
+            .filter(|line| !provenance::is_last_set(line))
+            .collect();
+        Ok(Provenance::new(definition, last_set.as_deref()))
+    }
+
+    /// Loads the definition of the user command `name` (such as `MyCommand`)
+    /// as printed by `:verbose command`, alongside the script and line that
+    /// defined it
+    ///
+    /// ### Notes
+    ///
+    /// * Returns an [`io::Error`] if the command does not exist
+    pub fn command_provenance(&self, name: &str) -> io::Result<Provenance<String>> {
+        let lines: Vec<String> = self
+            .eval::<Option<_>>(&format!(
+                "exists(':' . {0}) == 2 ? s:verbose('command ' . {0}) : v:null",
+                process::vim_string_literal(name)
+            ))?
+            .ok_or_else(|| not_found("Command", name))?;
+
+        let (i, definition) =
+            provenance::find_command(&lines, name).ok_or_else(|| not_found("Command", name))?;
+        Ok(Provenance::new(
+            definition,
+            provenance::last_set_after(&lines, i),
+        ))
+    }
+
+    /// Loads the right-hand side of the mapping of `lhs` (such as
+    /// `<leader>f`) within `mode` (such as `n`, `i` or an empty string for
+    /// normal, visual and operator-pending) alongside the script and line
+    /// that defined it, as reported by `:verbose map`
+    ///
+    /// ### Notes
+    ///
+    /// * `mode` is one of the modes supported by `maparg()`: an empty string,
+    ///   `n`, `v`, `x`, `s`, `o`, `i`, `c`, `l`, `t` or `!` (insert and
+    ///   command-line), where any other mode returns an [`io::Error`]
+    /// * Buffer-local mappings take precedence over global ones, matching
+    ///   `maparg()`
+    /// * Mappings defined with a Lua callback have an empty right-hand side
+    /// * Returns an [`io::Error`] if there is no such mapping
+    pub fn mapping_provenance(&self, mode: &str, lhs: &str) -> io::Result<Provenance<String>> {
+        #[derive(Deserialize)]
+        struct RawMapping {
+            lhs: String,
+            #[serde(default)]
+            rhs: String,
+        }
+
+        let map_cmd = match mode {
+            "" => "map",
+            "n" => "nmap",
+            "v" => "vmap",
+            "x" => "xmap",
+            "s" => "smap",
+            "o" => "omap",
+            "i" => "imap",
+            "c" => "cmap",
+            "l" => "lmap",
+            "t" => "tmap",
+            "!" => "map!",
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Unsupported mapping mode \"{}\"", mode),
+                ))
+            }
+        };
+
+        // Characters that would end the :map command are escaped with CTRL-V
+        // rather than written as <Bar>, which is taken literally when '<' is
+        // within 'cpoptions' (as it is when vim is started with -u)
+        let escaped_lhs = lhs.replace('|', "\u{16}|").replace('\n', "\u{16}\n");
+
+        let (mapping, lines): (Option<RawMapping>, Vec<String>) = self.eval(&format!(
+            "[map([maparg({0}, {1}, 0, 1)], {{_, m -> empty(m) ? v:null : m}})[0], \
+             s:verbose({2})]",
+            process::vim_string_literal(lhs),
+            process::vim_string_literal(mode),
+            process::vim_string_literal(&format!("{} {}", map_cmd, escaped_lhs))
+        ))?;
+
+        let mapping = mapping.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Mapping \"{}\" not found for mode \"{}\"", lhs, mode),
+            )
+        })?;
+        let last_set = provenance::find_mapping(&lines, &mapping.lhs)
+            .and_then(|i| provenance::last_set_after(&lines, i));
+        Ok(Provenance::new(mapping.rhs, last_set))
+    }
+
+    /// Opens the file at `path` after the config is sourced and loads the
+    /// settings the editor would use to edit it, such as the `shiftwidth`
+    /// set by an ftplugin or the `textwidth` set by a modeline
+    ///
+    /// ### Notes
+    ///
+    /// * Filetype detection, ftplugins, modelines and editorconfig only apply
+    ///   if the config enables them (such as `:filetype plugin indent on`),
+    ///   matching how the user's editor would behave
+    /// * The file does not need to exist, in which case the settings are
+    ///   those of a new file with that name
+    /// * No swap file is created for the file
+    pub fn settings_for_file(&self, path: impl AsRef<Path>) -> io::Result<FileSettings> {
+        #[derive(Deserialize)]
+        struct RawFileSettings {
+            filetype: String,
+            options: BTreeMap<String, RawOption>,
+            vars: Value,
+        }
+
+        let raw: RawFileSettings = self.eval(&format!(
+            "s:file_settings({})",
+            path_literal(path.as_ref())?
+        ))?;
+
+        Ok(FileSettings {
+            filetype: raw.filetype,
+            options: raw
+                .options
+                .into_iter()
+                .map(|(name, raw)| Ok((name, raw.into_value()?)))
+                .collect::<io::Result<_>>()?,
+            vars: into_vars(VimValue::from_tagged(raw.vars)?)?,
+        })
+    }
+
+    /// Detects the filetype the editor would assign to the file at `path`
+    /// after the config is sourced, honoring `ftdetect` scripts,
+    /// `vim.filetype.add()` rules, modelines and detection based on the
+    /// file's contents, returning `None` if no filetype is detected
+    ///
+    /// ### Notes
+    ///
+    /// * If `contents` is provided, it is used in place of the file's
+    ///   contents (which need not exist), otherwise the file is read
+    /// * Filetype detection only happens if the config enables it (such as
+    ///   `:filetype on`), which neovim does by default
+    pub fn detect_filetype(
+        &self,
+        path: impl AsRef<Path>,
+        contents: Option<&str>,
+    ) -> io::Result<Option<String>> {
+        let path = path_literal(path.as_ref())?;
+        let lines = match contents {
+            Some(contents) => literal::to_string(&contents.lines().collect::<Vec<_>>())
+                .map_err(|x| io::Error::new(io::ErrorKind::InvalidInput, x))?,
+            None => String::from("v:null"),
+        };
+
+        let filetype: String =
+            self.eval(&format!("[s:open({}, {}), &filetype][1]", path, lines))?;
+        Ok(Some(filetype).filter(|x| !x.is_empty()))
+    }
+
+    /// Loads every highlight group after the config (and with it the
+    /// colorscheme) is sourced, alongside the terminal's 16 colors
+    ///
+    /// ### Notes
+    ///
+    /// * Uses `nvim_get_hl()` for neovim (requiring neovim 0.9 or later) and
+    ///   `hlget()` for vim
+    /// * Colors are RGB if `'termguicolors'` is set and palette indices
+    ///   otherwise, where color names are converted using `v:colornames`
+    /// * Linked groups report the colors and attributes of the group they
+    ///   resolve to alongside the name of the group they link to
+    /// * vim is started with `t_Co` set to 256 (as it would be within a 256
+    ///   color terminal) so that colorschemes define their cterm colors,
+    ///   which also applies to any checks of `&t_Co` within the config
+    pub fn highlights(&self) -> io::Result<Palette> {
+        let raw: RawPalette = self.eval_with_colors("s:highlights()")?;
+        Ok(Palette::from(raw))
+    }
+
+    /// Highlights `source` as the editor would when editing a buffer with
+    /// `filetype` (such as `rust`), returning spans that cover every
+    /// character of each line alongside the group and colors used to draw it
+    ///
+    /// ### Notes
+    ///
+    /// * Uses `synID()` for vim, meaning syntax highlighting must be enabled
+    ///   by the config (such as with `:syntax on`)
+    /// * Uses `vim.inspect_pos()` for neovim (requiring neovim 0.9 or later),
+    ///   which also includes treesitter captures when treesitter has been
+    ///   started for the filetype, semantic tokens and extmarks
+    /// * Colors are resolved (and vim is started) the same way as
+    ///   [`Self::highlights`]
+    ///
+    /// ### Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> std::io::Result<()> {
+    /// use vimvar::Editor;
+    ///
+    /// let spans = Editor::detect()?.highlight_text("vim", "let x = 1")?;
+    /// for span in spans {
+    ///     println!("{:?} {:?} {:?}", span.text, span.group, span.style.fg);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn highlight_text(&self, filetype: &str, source: &str) -> io::Result<Vec<StyledSpan>> {
+        let file = text_file(source)?;
+        let raw: RawHighlightedText = self.eval_with_colors(&format!(
+            "s:highlight_text({}, {}, {})",
+            process::vim_string_literal(filetype),
+            path_literal(file.path())?,
+            process::vim_string_literal(process::SPANS_LUA)
+        ))?;
+        Ok(raw.into_spans(source))
+    }
+
+    /// Reindents every line of `text` as the editor would within a buffer
+    /// with `filetype` (such as `rust`) by running `gg=G`, returning the
+    /// resulting text
+    ///
+    /// ### Notes
+    ///
+    /// * Indentation is determined by the `'indentexpr'`, `'cindent'` and
+    ///   related options set by the config and indent scripts, which are only
+    ///   loaded if the config enables them (such as with
+    ///   `:filetype plugin indent on`), which neovim does by default
+    pub fn reindent(&self, filetype: &str, text: &str) -> io::Result<String> {
+        self.format(filetype, text, FormatCommand::Indent)
+    }
+
+    /// Formats `text` within a buffer with `filetype` (such as `markdown`)
+    /// by running `cmd`, returning the resulting text
+    ///
+    /// ### Notes
+    ///
+    /// * The buffer is set up the same way as [`Self::reindent`], so options
+    ///   such as `'textwidth'` and `'formatprg'` set by ftplugins apply
+    /// * Line endings of the result match those of `text`, including whether
+    ///   it ends with a newline
+    ///
+    /// ### Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> std::io::Result<()> {
+    /// use vimvar::{Editor, FormatCommand};
+    ///
+    /// let editor = Editor::detect()?;
+    /// let text = editor.format("markdown", "Some long paragraph\n", FormatCommand::Format)?;
+    /// let text = editor.format("python", &text, FormatCommand::Ex("%s/\\s\\+$//e"))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn format(&self, filetype: &str, text: &str, cmd: FormatCommand<'_>) -> io::Result<String> {
+        let file = text_file(text)?;
+        let lines: Vec<String> = self.eval(&format!(
+            "s:format({}, {}, {})",
+            process::vim_string_literal(filetype),
+            path_literal(file.path())?,
+            process::vim_string_literal(&cmd.to_ex())
+        ))?;
+
+        let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+        let mut result = lines.join(newline);
+        if text.ends_with('\n') {
+            result.push_str(newline);
+        }
+        Ok(result)
+    }
+
+    /// Loads the `'runtimepath'` of the editor after the config is sourced,
+    /// which reflects any changes made by the user or their plugin manager
+    pub fn runtimepath(&self) -> io::Result<Vec<PathBuf>> {
+        let rtp: String = self.eval("&rtp")?;
+
+        Ok(split_path_option(&rtp)
+            .into_iter()
+            .map(PathBuf::from)
+            .collect())
+    }
+
+    /// Searches the editor's runtimepath for files matching `pattern` (such
+    /// as `colors/*.vim` or `lua/myplugin/*.lua`), returning the absolute
+    /// path of each match in runtimepath order
+    ///
+    /// ### Notes
+    ///
+    /// * Uses `nvim_get_runtime_file()` for neovim and `globpath()` for vim
+    /// * The config is sourced prior to searching, so directories added to
+    ///   the runtimepath by plugin managers are included
+    pub fn find_runtime_files(&self, pattern: &str) -> io::Result<Vec<PathBuf>> {
+        let pattern = process::vim_string_literal(pattern);
+        let expr = match self.cmd {
+            Cmd::Neovim => format!("nvim_get_runtime_file({}, v:true)", pattern),
+            Cmd::Vim => format!("globpath(&rtp, {}, 0, 1)", pattern),
+        };
+
+        self.eval(&expr)
+    }
+}
+
+/// Returns an expression producing a copy of the Dict of variables within
+/// `scope`, limited to those whose names start with `prefix`
+fn vars_expr(scope: Scope, prefix: Option<&str>) -> io::Result<String> {
+    match scope {
+        Scope::Global | Scope::Buffer | Scope::Window | Scope::Tabpage | Scope::Vim => {}
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Cannot list variables within scope \"{}\"", scope),
+            ))
+        }
+    }
+
+    Ok(match prefix {
+        Some(prefix) => format!(
+            "filter(copy({}), {{k -> stridx(k, {}) == 0}})",
+            scope,
+            process::vim_string_literal(prefix)
+        ),
+        None => format!("copy({})", scope),
+    })
+}
+
+/// Converts `value`, the Dict of a scope's variables, into its entries
+fn into_vars(value: VimValue) -> io::Result<BTreeMap<String, VimValue>> {
+    match value {
+        VimValue::Dict(vars) => Ok(vars),
+        x => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Expected dict of variables, but got {}", x.kind()),
+        )),
+    }
+}
+
+/// Creates an [`io::Error`] reporting that the `kind` of thing named `name`
+/// (such as a function) does not exist
+fn not_found(kind: &str, name: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("{} \"{}\" not found", kind, name),
+    )
+}
+
+/// Writes `text` into a temporary file to be read by the editor, as it may
+/// exceed the size allowed for an environment variable
+fn text_file(text: &str) -> io::Result<tempfile::NamedTempFile> {
+    let mut file = tempfile::NamedTempFile::new()?;
+    file.write_all(text.as_bytes())?;
+    file.flush()?;
+    Ok(file)
+}
+
+/// Converts `path` into a Vimscript string literal
+fn path_literal(path: &Path) -> io::Result<String> {
+    let path = path
+        .to_str()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Path is not valid UTF-8"))?;
+    Ok(process::vim_string_literal(path))
+}
+
+/// Splits a comma-separated path option such as `'runtimepath'`, where a
+/// backslash can be used to include a literal comma within a path
+fn split_path_option(value: &str) -> Vec<String> {
+    let mut paths = value
+        .parse::<CommaList>()
+        .map(CommaList::into_items)
+        .unwrap_or_default();
+
+    paths.retain(|path| !path.is_empty());
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vars_expr_should_filter_by_prefix() {
+        assert_eq!(vars_expr(Scope::Global, None).unwrap(), "copy(g:)");
+        assert_eq!(
+            vars_expr(Scope::Buffer, Some("it's_")).unwrap(),
+            "filter(copy(b:), {k -> stridx(k, 'it''s_') == 0})"
+        );
+    }
+
+    #[test]
+    fn vars_expr_should_fail_for_scopes_that_cannot_be_listed() {
+        for scope in [
+            Scope::Nothing,
+            Scope::Local,
+            Scope::Script,
+            Scope::FunctionArg,
+        ] {
+            assert!(vars_expr(scope, None).is_err(), "{:?} was listed", scope);
+        }
+    }
+
+    #[test]
+    fn split_path_option_should_split_on_commas() {
+        assert_eq!(
+            split_path_option("~/.vim,/usr/share/vim/vim90,~/.vim/after"),
+            vec!["~/.vim", "/usr/share/vim/vim90", "~/.vim/after"]
+        );
+    }
+
+    #[test]
+    fn split_path_option_should_support_escaped_commas() {
+        assert_eq!(
+            split_path_option(r"/a\,b,/c"),
+            vec!["/a,b".to_string(), "/c".to_string()]
+        );
+    }
+
+    #[test]
+    fn split_path_option_should_skip_empty_entries() {
+        assert_eq!(split_path_option(""), Vec::<String>::new());
+        assert_eq!(split_path_option("/a,,/b"), vec!["/a", "/b"]);
+    }
+}
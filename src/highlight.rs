@@ -0,0 +1,602 @@
+use serde::Deserialize;
+use serde_json::Value;
+use std::{collections::BTreeMap, convert::TryFrom, fmt, io, str::FromStr};
+
+mod export;
+pub use export::*;
+
+/// Default colors of xterm's first 16 palette indices
+const XTERM_COLORS: [u32; 16] = [
+    0x000000, 0xcd0000, 0x00cd00, 0xcdcd00, 0x0000ee, 0xcd00cd, 0x00cdcd, 0xe5e5e5, 0x7f7f7f,
+    0xff0000, 0x00ff00, 0xffff00, 0x5c5cff, 0xff00ff, 0x00ffff, 0xffffff,
+];
+
+/// Represents a color used by a highlight group
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Color {
+    /// 24-bit color, used when `'termguicolors'` is set
+    Rgb(u8, u8, u8),
+
+    /// Index into the terminal's palette (such as 1 or 235), used when
+    /// `'termguicolors'` is not set
+    Indexed(u8),
+}
+
+impl Color {
+    /// Creates an RGB color from a number such as `0xff8800`
+    ///
+    /// ### Examples
+    ///
+    /// ```
+    /// use vimvar::highlight::Color;
+    ///
+    /// assert_eq!(Color::from_u32(0xff8800), Color::Rgb(0xff, 0x88, 0x00));
+    /// ```
+    pub fn from_u32(rgb: u32) -> Self {
+        Self::Rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+    }
+
+    /// Converts into red, green and blue components, where palette indices
+    /// use the colors of xterm's default 256 color palette
+    ///
+    /// ### Examples
+    ///
+    /// ```
+    /// use vimvar::highlight::Color;
+    ///
+    /// assert_eq!(Color::Rgb(1, 2, 3).to_rgb(), (1, 2, 3));
+    /// assert_eq!(Color::Indexed(1).to_rgb(), (0xcd, 0, 0));
+    /// assert_eq!(Color::Indexed(81).to_rgb(), (0x5f, 0xd7, 0xff));
+    /// assert_eq!(Color::Indexed(235).to_rgb(), (0x26, 0x26, 0x26));
+    /// ```
+    pub fn to_rgb(&self) -> (u8, u8, u8) {
+        const CUBE_LEVELS: [u8; 6] = [0, 0x5f, 0x87, 0xaf, 0xd7, 0xff];
+
+        match *self {
+            Self::Rgb(r, g, b) => (r, g, b),
+            Self::Indexed(i) if i < 16 => Self::from_u32(XTERM_COLORS[usize::from(i)]).to_rgb(),
+            Self::Indexed(i) if i < 232 => {
+                let i = usize::from(i - 16);
+                (
+                    CUBE_LEVELS[i / 36],
+                    CUBE_LEVELS[i / 6 % 6],
+                    CUBE_LEVELS[i % 6],
+                )
+            }
+            Self::Indexed(i) => {
+                let level = 8 + 10 * (i - 232);
+                (level, level, level)
+            }
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = io::Error;
+
+    /// Parses either `#rrggbb` or a palette index
+    ///
+    /// ### Examples
+    ///
+    /// ```
+    /// use vimvar::highlight::Color;
+    ///
+    /// assert_eq!("#ff8800".parse::<Color>().unwrap(), Color::Rgb(0xff, 0x88, 0x00));
+    /// assert_eq!("235".parse::<Color>().unwrap(), Color::Indexed(235));
+    /// assert!("Red".parse::<Color>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("Invalid color {}", s));
+
+        match s.strip_prefix('#') {
+            Some(hex) if hex.len() == 6 => u32::from_str_radix(hex, 16)
+                .map(Self::from_u32)
+                .map_err(|_| invalid()),
+            Some(_) => Err(invalid()),
+            None => s.parse().map(Self::Indexed).map_err(|_| invalid()),
+        }
+    }
+}
+
+impl fmt::Display for Color {
+    /// Writes color as `#rrggbb` or as a palette index
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rgb(r, g, b) => write!(f, "#{:02x}{:02x}{:02x}", r, g, b),
+            Self::Indexed(x) => write!(f, "{}", x),
+        }
+    }
+}
+
+/// Represents the text attributes of a highlight group
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Attributes {
+    /// Bold text (`bold`)
+    pub bold: bool,
+
+    /// Italic text (`italic`)
+    pub italic: bool,
+
+    /// Single straight underline (`underline`)
+    pub underline: bool,
+
+    /// Curly underline (`undercurl`)
+    pub undercurl: bool,
+
+    /// Double underline (`underdouble`)
+    pub underdouble: bool,
+
+    /// Dotted underline (`underdotted`)
+    pub underdotted: bool,
+
+    /// Dashed underline (`underdashed`)
+    pub underdashed: bool,
+
+    /// Line through the text (`strikethrough`)
+    pub strikethrough: bool,
+
+    /// Foreground and background colors swapped (`reverse` or `inverse`)
+    pub reverse: bool,
+
+    /// The terminal's standout mode (`standout`)
+    pub standout: bool,
+
+    /// Attributes are replaced rather than combined with those of other
+    /// groups drawn at the same position (`nocombine`)
+    pub nocombine: bool,
+}
+
+impl Attributes {
+    /// Creates attributes from a Dict of attribute names to booleans, where
+    /// other entries (such as colors) are ignored
+    fn from_map(map: &BTreeMap<String, Value>) -> Self {
+        let is_set = |name: &str| map.get(name) == Some(&Value::Bool(true));
+        Self {
+            bold: is_set("bold"),
+            italic: is_set("italic"),
+            underline: is_set("underline"),
+            undercurl: is_set("undercurl"),
+            underdouble: is_set("underdouble"),
+            underdotted: is_set("underdotted"),
+            underdashed: is_set("underdashed"),
+            strikethrough: is_set("strikethrough"),
+            reverse: is_set("reverse") || is_set("inverse"),
+            standout: is_set("standout"),
+            nocombine: is_set("nocombine"),
+        }
+    }
+}
+
+/// Represents a highlight group with any links resolved
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Highlight {
+    /// Foreground color, or `None` to use the default
+    pub fg: Option<Color>,
+
+    /// Background color, or `None` to use the default
+    pub bg: Option<Color>,
+
+    /// Color of underlines and undercurls, or `None` to use the foreground
+    pub sp: Option<Color>,
+
+    /// Text attributes such as bold or undercurl
+    pub attrs: Attributes,
+
+    /// Group this group links to (such as `Statement` for `Keyword`), whose
+    /// colors and attributes are reflected in this group
+    pub link: Option<String>,
+}
+
+/// Represents the highlight groups of the editor once the config (and with
+/// it the colorscheme) has been loaded
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Palette {
+    /// Name of the loaded colorscheme, if any
+    pub colorscheme: Option<String>,
+
+    /// Value of `'background'`, either `dark` or `light`
+    pub background: String,
+
+    /// Whether `'termguicolors'` is set, meaning colors are [`Color::Rgb`]
+    /// rather than [`Color::Indexed`]
+    pub termguicolors: bool,
+
+    /// Every highlight group keyed by name
+    pub groups: BTreeMap<String, Highlight>,
+
+    /// The 16 colors used by the terminal emulator, as defined by
+    /// `g:terminal_color_0` to `g:terminal_color_15` (neovim) or
+    /// `g:terminal_ansi_colors` (vim), with `None` for those not defined
+    pub terminal_colors: Vec<Option<Color>>,
+}
+
+impl Palette {
+    /// Returns the highlight group `name` (such as `Normal` or `Comment`)
+    pub fn group(&self, name: &str) -> Option<&Highlight> {
+        self.groups.get(name)
+    }
+
+    /// Returns the colors and attributes used to draw text with the group
+    /// `name`, where colors that are not set fall back to those of `Normal`
+    ///
+    /// ### Notes
+    ///
+    /// * Treesitter groups that are not defined fall back to more general
+    ///   groups the same way neovim does (`@keyword.lua` uses `@keyword`)
+    /// * Groups that do not exist use the style of `Normal`
+    pub fn style(&self, name: &str) -> Highlight {
+        let mut name = name;
+        let group = loop {
+            if let Some(group) = self.group(name) {
+                break Some(group);
+            }
+            match name.rfind('.') {
+                Some(i) if name.starts_with('@') => name = &name[..i],
+                _ => break None,
+            }
+        };
+
+        let normal = self.group("Normal");
+        let mut style = group.or(normal).cloned().unwrap_or_default();
+        style.fg = style.fg.or_else(|| normal.and_then(|x| x.fg));
+        style.bg = style.bg.or_else(|| normal.and_then(|x| x.bg));
+        style
+    }
+}
+
+/// Represents a span of text within a single line drawn using one highlight
+/// group
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StyledSpan {
+    /// Index of the line containing the span, starting at 0
+    pub line: usize,
+
+    /// Byte offset within the line where the span starts
+    pub start: usize,
+
+    /// Byte offset within the line where the span ends (exclusive)
+    pub end: usize,
+
+    /// Text of the span
+    pub text: String,
+
+    /// Highlight group used to draw the span (such as `vimCommand` or
+    /// `@keyword.lua`), or `None` for text that is not highlighted
+    pub group: Option<String>,
+
+    /// Colors and attributes of the group, see [`Palette::style`]
+    pub style: Highlight,
+}
+
+/// Represents the result of `s:highlight_text()`
+#[derive(Deserialize)]
+pub(crate) struct RawHighlightedText {
+    spans: Vec<(usize, usize, usize, String)>,
+    palette: RawPalette,
+}
+
+impl RawHighlightedText {
+    /// Converts into spans whose text is taken from the lines of `source`
+    pub(crate) fn into_spans(self, source: &str) -> Vec<StyledSpan> {
+        let lines: Vec<&str> = source.lines().collect();
+        let palette = Palette::from(self.palette);
+
+        self.spans
+            .into_iter()
+            .map(|(line, start, end, group)| StyledSpan {
+                line,
+                start,
+                end,
+                text: lines
+                    .get(line)
+                    .and_then(|x| x.get(start..end))
+                    .unwrap_or_default()
+                    .to_string(),
+                style: palette.style(&group),
+                group: Some(group).filter(|x| !x.is_empty()),
+            })
+            .collect()
+    }
+}
+
+/// Represents the palette as reported by `s:highlights()`
+#[derive(Deserialize)]
+pub(crate) struct RawPalette {
+    colorscheme: String,
+    background: String,
+    termguicolors: bool,
+    groups: BTreeMap<String, RawHighlight>,
+    terminal_colors: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct RawHighlight {
+    link: String,
+    gui: RawColors,
+    cterm: RawColors,
+}
+
+/// Represents colors as either numbers (neovim) or strings (vim), where
+/// strings may also be `fg`, `bg` or empty
+#[derive(Deserialize)]
+struct RawColors {
+    fg: Value,
+    bg: Value,
+    sp: Value,
+    attrs: BTreeMap<String, Value>,
+}
+
+/// Represents the color of a group before `fg` and `bg` are resolved using
+/// the `Normal` group
+#[derive(Copy, Clone)]
+enum RawColor {
+    Color(Color),
+    Fg,
+    Bg,
+}
+
+impl RawColor {
+    fn parse(value: &Value, gui: bool) -> Option<Self> {
+        match value {
+            Value::Number(x) => {
+                let x = x.as_u64()?;
+                Some(Self::Color(if gui {
+                    Color::from_u32(x as u32)
+                } else {
+                    Color::Indexed(u8::try_from(x).ok()?)
+                }))
+            }
+            Value::String(x) if x.eq_ignore_ascii_case("fg") => Some(Self::Fg),
+            Value::String(x) if x.eq_ignore_ascii_case("bg") => Some(Self::Bg),
+
+            // Includes NONE as well as color names that could not be resolved
+            Value::String(x) => x.parse().ok().map(Self::Color),
+            _ => None,
+        }
+    }
+
+    fn resolve(color: Option<Self>, normal: &Highlight) -> Option<Color> {
+        match color? {
+            Self::Color(x) => Some(x),
+            Self::Fg => normal.fg,
+            Self::Bg => normal.bg,
+        }
+    }
+}
+
+impl RawHighlight {
+    fn into_highlight(self, gui: bool, normal: &Highlight) -> Highlight {
+        let colors = if gui { &self.gui } else { &self.cterm };
+        let parse = |value| RawColor::resolve(RawColor::parse(value, gui), normal);
+
+        Highlight {
+            fg: parse(&colors.fg),
+            bg: parse(&colors.bg),
+            sp: parse(&colors.sp),
+            attrs: Attributes::from_map(&colors.attrs),
+            link: Some(self.link).filter(|x| !x.is_empty()),
+        }
+    }
+}
+
+impl From<RawPalette> for Palette {
+    fn from(mut raw: RawPalette) -> Self {
+        let gui = raw.termguicolors;
+
+        // Normal cannot use fg or bg itself, so it is resolved first
+        let normal = raw
+            .groups
+            .remove("Normal")
+            .map(|x| x.into_highlight(gui, &Highlight::default()))
+            .unwrap_or_default();
+
+        let mut groups: BTreeMap<String, Highlight> = raw
+            .groups
+            .into_iter()
+            .map(|(name, x)| (name, x.into_highlight(gui, &normal)))
+            .collect();
+        groups.insert(String::from("Normal"), normal);
+
+        Self {
+            colorscheme: Some(raw.colorscheme).filter(|x| !x.is_empty()),
+            background: raw.background,
+            termguicolors: gui,
+            groups,
+            terminal_colors: raw.terminal_colors.iter().map(|x| x.parse().ok()).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn raw_palette(termguicolors: bool, groups: Value) -> RawPalette {
+        serde_json::from_value(json!({
+            "colorscheme": "desert",
+            "background": "dark",
+            "termguicolors": termguicolors,
+            "groups": groups,
+            "terminal_colors": ["#000000", "", "#FF0000"],
+        }))
+        .unwrap()
+    }
+
+    fn raw_group(link: &str, gui: Value, cterm: Value) -> Value {
+        json!({ "link": link, "gui": gui, "cterm": cterm })
+    }
+
+    #[test]
+    fn palette_should_use_gui_colors_if_termguicolors_is_set() {
+        let palette = Palette::from(raw_palette(
+            true,
+            json!({
+                // neovim reports colors as numbers alongside other entries
+                "Comment": raw_group(
+                    "",
+                    json!({ "fg": 0x6dceeb, "bg": "", "sp": "", "attrs": { "fg": 1, "italic": true } }),
+                    json!({ "fg": 81, "bg": "", "sp": "", "attrs": { "bold": true } }),
+                ),
+                // vim reports colors as strings
+                "Error": raw_group(
+                    "",
+                    json!({ "fg": "#ffffff", "bg": "NONE", "sp": "#ff0000", "attrs": { "undercurl": true } }),
+                    json!({ "fg": "15", "bg": "", "sp": "", "attrs": {} }),
+                ),
+            }),
+        ));
+
+        assert_eq!(palette.colorscheme.as_deref(), Some("desert"));
+        assert_eq!(
+            palette.group("Comment"),
+            Some(&Highlight {
+                fg: Some(Color::Rgb(0x6d, 0xce, 0xeb)),
+                attrs: Attributes {
+                    italic: true,
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            palette.group("Error"),
+            Some(&Highlight {
+                fg: Some(Color::Rgb(0xff, 0xff, 0xff)),
+                sp: Some(Color::Rgb(0xff, 0, 0)),
+                attrs: Attributes {
+                    undercurl: true,
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            palette.terminal_colors,
+            vec![
+                Some(Color::Rgb(0, 0, 0)),
+                None,
+                Some(Color::Rgb(0xff, 0, 0))
+            ]
+        );
+    }
+
+    #[test]
+    fn palette_should_use_cterm_colors_if_termguicolors_is_not_set() {
+        let palette = Palette::from(raw_palette(
+            false,
+            json!({
+                "Comment": raw_group(
+                    "",
+                    json!({ "fg": 0x6dceeb, "bg": "", "sp": "", "attrs": { "italic": true } }),
+                    json!({ "fg": 81, "bg": "236", "sp": "", "attrs": { "bold": true } }),
+                ),
+            }),
+        ));
+
+        assert_eq!(
+            palette.group("Comment"),
+            Some(&Highlight {
+                fg: Some(Color::Indexed(81)),
+                bg: Some(Color::Indexed(236)),
+                attrs: Attributes {
+                    bold: true,
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn style_should_fall_back_to_general_groups_and_normal() {
+        let mut palette = Palette::default();
+        let group = |fg: u32, bg: Option<u32>| Highlight {
+            fg: Some(Color::from_u32(fg)),
+            bg: bg.map(Color::from_u32),
+            ..Default::default()
+        };
+        palette
+            .groups
+            .insert("Normal".to_string(), group(0xeeeeee, Some(0x111111)));
+        palette
+            .groups
+            .insert("@keyword".to_string(), group(0xff0000, None));
+
+        assert_eq!(
+            palette.style("@keyword.return.lua"),
+            group(0xff0000, Some(0x111111))
+        );
+        assert_eq!(
+            palette.style("Missing.group"),
+            group(0xeeeeee, Some(0x111111))
+        );
+        assert_eq!(palette.style(""), group(0xeeeeee, Some(0x111111)));
+    }
+
+    #[test]
+    fn highlighted_text_should_take_text_of_spans_from_source() {
+        let raw: RawHighlightedText = serde_json::from_value(json!({
+            "spans": [[0, 0, 3, "vimLet"], [0, 3, 4, ""], [1, 5, 9, "vimString"]],
+            "palette": {
+                "colorscheme": "",
+                "background": "dark",
+                "termguicolors": true,
+                "groups": {},
+                "terminal_colors": [],
+            },
+        }))
+        .unwrap();
+
+        let spans = raw.into_spans("let x\r\necho \"é\"\n");
+        let texts: Vec<(&str, Option<&str>)> = spans
+            .iter()
+            .map(|x| (x.text.as_str(), x.group.as_deref()))
+            .collect();
+        assert_eq!(
+            texts,
+            vec![
+                ("let", Some("vimLet")),
+                (" ", None),
+                ("\"é\"", Some("vimString"))
+            ]
+        );
+    }
+
+    #[test]
+    fn palette_should_resolve_fg_and_bg_using_normal() {
+        let palette = Palette::from(raw_palette(
+            true,
+            json!({
+                "Normal": raw_group(
+                    "",
+                    json!({ "fg": "#ffffff", "bg": "#333333", "sp": "", "attrs": {} }),
+                    json!({ "fg": "", "bg": "", "sp": "", "attrs": {} }),
+                ),
+                "Inverted": raw_group(
+                    "Other",
+                    json!({ "fg": "bg", "bg": "fg", "sp": "", "attrs": { "inverse": true } }),
+                    json!({ "fg": "", "bg": "", "sp": "", "attrs": {} }),
+                ),
+            }),
+        ));
+
+        assert_eq!(
+            palette.group("Inverted"),
+            Some(&Highlight {
+                fg: Some(Color::Rgb(0x33, 0x33, 0x33)),
+                bg: Some(Color::Rgb(0xff, 0xff, 0xff)),
+                sp: None,
+                attrs: Attributes {
+                    reverse: true,
+                    ..Default::default()
+                },
+                link: Some("Other".to_string()),
+            })
+        );
+        assert_eq!(
+            palette.group("Normal").and_then(|x| x.fg),
+            Some(Color::Rgb(0xff, 0xff, 0xff))
+        );
+    }
+}
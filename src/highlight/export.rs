@@ -0,0 +1,627 @@
+use super::{Color, Highlight, Palette};
+use serde_json::{json, Map, Value};
+use std::{fmt, io, str::FromStr};
+
+/// Represents a kind of theme file that a [`Palette`] can be exported as
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ThemeFormat {
+    /// Colors section of an Alacritty config (`alacritty.toml`)
+    Alacritty,
+
+    /// Kitty theme included from `kitty.conf`
+    Kitty,
+
+    /// WezTerm color scheme placed within its `colors` directory
+    WezTerm,
+
+    /// TextMate theme used by `bat` and other syntect-based tools
+    TmTheme,
+
+    /// JSON object of the terminal colors and every highlight group
+    Json,
+}
+
+impl ThemeFormat {
+    /// Converts to a str representing the format
+    ///
+    /// ### Examples
+    ///
+    /// ```
+    /// use vimvar::highlight::ThemeFormat;
+    ///
+    /// assert_eq!(ThemeFormat::Alacritty.as_str(), "alacritty");
+    /// assert_eq!(ThemeFormat::Kitty.as_str(), "kitty");
+    /// assert_eq!(ThemeFormat::WezTerm.as_str(), "wezterm");
+    /// assert_eq!(ThemeFormat::TmTheme.as_str(), "tmtheme");
+    /// assert_eq!(ThemeFormat::Json.as_str(), "json");
+    /// ```
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Alacritty => "alacritty",
+            Self::Kitty => "kitty",
+            Self::WezTerm => "wezterm",
+            Self::TmTheme => "tmtheme",
+            Self::Json => "json",
+        }
+    }
+
+    /// Returns the file extension typically used by the format
+    ///
+    /// ### Examples
+    ///
+    /// ```
+    /// use vimvar::highlight::ThemeFormat;
+    ///
+    /// assert_eq!(ThemeFormat::Alacritty.extension(), "toml");
+    /// assert_eq!(ThemeFormat::Kitty.extension(), "conf");
+    /// assert_eq!(ThemeFormat::WezTerm.extension(), "toml");
+    /// assert_eq!(ThemeFormat::TmTheme.extension(), "tmTheme");
+    /// assert_eq!(ThemeFormat::Json.extension(), "json");
+    /// ```
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Alacritty | Self::WezTerm => "toml",
+            Self::Kitty => "conf",
+            Self::TmTheme => "tmTheme",
+            Self::Json => "json",
+        }
+    }
+}
+
+impl fmt::Display for ThemeFormat {
+    /// Writes format using the [`Self::as_str`] representation
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for ThemeFormat {
+    type Err = io::Error;
+
+    /// Parses the [`Self::as_str`] representation, ignoring case
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "alacritty" => Ok(Self::Alacritty),
+            "kitty" => Ok(Self::Kitty),
+            "wezterm" => Ok(Self::WezTerm),
+            "tmtheme" => Ok(Self::TmTheme),
+            "json" => Ok(Self::Json),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Unknown theme format {}", s),
+            )),
+        }
+    }
+}
+
+/// Names of the terminal's 8 normal colors in order, where the bright colors
+/// use the same names
+const ANSI_NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+/// Highlight groups exported to a TextMate theme alongside the scopes they
+/// color, ordered from general to specific as later rules take precedence
+const TM_SCOPES: &[(&str, &str)] = &[
+    ("Comment", "comment"),
+    ("SpecialComment", "comment.block.documentation"),
+    ("Constant", "constant"),
+    ("Number", "constant.numeric"),
+    ("Float", "constant.numeric.float"),
+    ("Boolean", "constant.language"),
+    ("Character", "constant.character"),
+    ("SpecialChar", "constant.character.escape"),
+    ("String", "string"),
+    ("Identifier", "variable"),
+    ("Function", "entity.name.function, support.function"),
+    ("Statement", "keyword"),
+    ("Keyword", "keyword, storage"),
+    ("Conditional", "keyword.control"),
+    ("Repeat", "keyword.control.loop"),
+    ("Exception", "keyword.control.exception"),
+    ("Include", "keyword.control.import"),
+    ("Operator", "keyword.operator"),
+    ("Label", "entity.name.label"),
+    ("PreProc", "meta.preprocessor"),
+    ("Macro", "entity.name.macro, support.macro"),
+    ("Type", "entity.name.type, support.type, storage.type"),
+    ("StorageClass", "storage.modifier"),
+    ("Special", "support"),
+    ("Tag", "entity.name.tag"),
+    ("Delimiter", "punctuation"),
+    ("Title", "markup.heading"),
+    ("Underlined", "markup.underline.link"),
+    ("DiffAdd", "markup.inserted"),
+    ("DiffDelete", "markup.deleted"),
+    ("DiffChange", "markup.changed"),
+    ("Error", "invalid"),
+];
+
+/// Colors shared by every terminal format
+struct TerminalColors {
+    foreground: String,
+    background: String,
+    cursor: String,
+    cursor_text: String,
+    selection_foreground: String,
+    selection_background: String,
+    ansi: Vec<String>,
+}
+
+impl Palette {
+    /// Exports the palette as a theme file for another tool, returning the
+    /// contents of the file
+    ///
+    /// ### Notes
+    ///
+    /// * Palette indices (used when `'termguicolors'` is not set) are
+    ///   converted into RGB using the terminal's 16 colors and the standard
+    ///   xterm 256 color palette
+    /// * The primary colors come from `Normal`, the cursor from `Cursor` and
+    ///   the selection from `Visual`, falling back to the terminal's colors
+    ///   based on `'background'` when not set
+    /// * Terminal colors not defined by the colorscheme use xterm's defaults
+    ///
+    /// ### Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> std::io::Result<()> {
+    /// use vimvar::{highlight::ThemeFormat, Editor};
+    ///
+    /// let palette = Editor::detect()?.highlights()?;
+    /// std::fs::write("theme.conf", palette.export(ThemeFormat::Kitty))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn export(&self, format: ThemeFormat) -> String {
+        match format {
+            ThemeFormat::Alacritty => self.to_alacritty(),
+            ThemeFormat::Kitty => self.to_kitty(),
+            ThemeFormat::WezTerm => self.to_wezterm(),
+            ThemeFormat::TmTheme => self.to_tmtheme(),
+            ThemeFormat::Json => self.to_json(),
+        }
+    }
+
+    /// Returns the name of the theme, being the colorscheme if loaded
+    fn theme_name(&self) -> &str {
+        self.colorscheme.as_deref().unwrap_or("vim")
+    }
+
+    /// Converts `color` into `#rrggbb`, using the terminal's colors for the
+    /// first 16 palette indices
+    fn hex(&self, color: Color) -> String {
+        let color = match color {
+            Color::Indexed(i) if i < 16 => self
+                .terminal_colors
+                .get(usize::from(i))
+                .copied()
+                .flatten()
+                .unwrap_or(color),
+            color => color,
+        };
+        let (r, g, b) = color.to_rgb();
+        Color::Rgb(r, g, b).to_string()
+    }
+
+    /// Returns the terminal's color `i` as `#rrggbb`
+    fn ansi(&self, i: u8) -> String {
+        self.hex(Color::Indexed(i))
+    }
+
+    /// Returns the foreground and background of `group` as `#rrggbb`, where
+    /// unset colors fall back to `Normal` and reverse swaps them
+    fn group_colors(&self, group: Option<&Highlight>) -> (String, String) {
+        let normal = self.group("Normal");
+        let dark = self.background != "light";
+        let fg = group
+            .and_then(|x| x.fg)
+            .or_else(|| normal.and_then(|x| x.fg))
+            .map(|x| self.hex(x))
+            .unwrap_or_else(|| self.ansi(if dark { 7 } else { 0 }));
+        let bg = group
+            .and_then(|x| x.bg)
+            .or_else(|| normal.and_then(|x| x.bg))
+            .map(|x| self.hex(x))
+            .unwrap_or_else(|| self.ansi(if dark { 0 } else { 15 }));
+
+        if group.is_some_and(|x| x.attrs.reverse) {
+            (bg, fg)
+        } else {
+            (fg, bg)
+        }
+    }
+
+    fn terminal_colors(&self) -> TerminalColors {
+        let (foreground, background) = self.group_colors(self.group("Normal"));
+
+        // Cursor is typically reversed, meaning its background is the cursor
+        let (cursor_text, cursor) = match self.group("Cursor") {
+            Some(x) if x.fg.is_some() || x.bg.is_some() => self.group_colors(Some(x)),
+            _ => (background.clone(), foreground.clone()),
+        };
+        let (selection_foreground, selection_background) = self.group_colors(self.group("Visual"));
+
+        TerminalColors {
+            foreground,
+            background,
+            cursor,
+            cursor_text,
+            selection_foreground,
+            selection_background,
+            ansi: (0..16).map(|i| self.ansi(i)).collect(),
+        }
+    }
+
+    fn to_alacritty(&self) -> String {
+        let colors = self.terminal_colors();
+        let mut s = format!("# {}\n", self.theme_name());
+
+        s.push_str("\n[colors.primary]\n");
+        s.push_str(&format!("background = \"{}\"\n", colors.background));
+        s.push_str(&format!("foreground = \"{}\"\n", colors.foreground));
+        s.push_str("\n[colors.cursor]\n");
+        s.push_str(&format!("text = \"{}\"\n", colors.cursor_text));
+        s.push_str(&format!("cursor = \"{}\"\n", colors.cursor));
+        s.push_str("\n[colors.selection]\n");
+        s.push_str(&format!("text = \"{}\"\n", colors.selection_foreground));
+        s.push_str(&format!(
+            "background = \"{}\"\n",
+            colors.selection_background
+        ));
+
+        for (section, ansi) in [("normal", &colors.ansi[..8]), ("bright", &colors.ansi[8..])] {
+            s.push_str(&format!("\n[colors.{}]\n", section));
+            for (name, color) in ANSI_NAMES.iter().zip(ansi) {
+                s.push_str(&format!("{} = \"{}\"\n", name, color));
+            }
+        }
+        s
+    }
+
+    fn to_kitty(&self) -> String {
+        let colors = self.terminal_colors();
+        let mut s = format!("# {}\n\n", self.theme_name());
+
+        s.push_str(&format!("foreground {}\n", colors.foreground));
+        s.push_str(&format!("background {}\n", colors.background));
+        s.push_str(&format!("cursor {}\n", colors.cursor));
+        s.push_str(&format!("cursor_text_color {}\n", colors.cursor_text));
+        s.push_str(&format!(
+            "selection_foreground {}\n",
+            colors.selection_foreground
+        ));
+        s.push_str(&format!(
+            "selection_background {}\n",
+            colors.selection_background
+        ));
+        for (i, color) in colors.ansi.iter().enumerate() {
+            s.push_str(&format!("color{} {}\n", i, color));
+        }
+        s
+    }
+
+    fn to_wezterm(&self) -> String {
+        let colors = self.terminal_colors();
+        let list = |colors: &[String]| {
+            let colors: Vec<String> = colors.iter().map(|x| format!("\"{}\"", x)).collect();
+            format!("[{}]", colors.join(", "))
+        };
+
+        let mut s = String::from("[colors]\n");
+        s.push_str(&format!("foreground = \"{}\"\n", colors.foreground));
+        s.push_str(&format!("background = \"{}\"\n", colors.background));
+        s.push_str(&format!("cursor_bg = \"{}\"\n", colors.cursor));
+        s.push_str(&format!("cursor_fg = \"{}\"\n", colors.cursor_text));
+        s.push_str(&format!("cursor_border = \"{}\"\n", colors.cursor));
+        s.push_str(&format!(
+            "selection_fg = \"{}\"\n",
+            colors.selection_foreground
+        ));
+        s.push_str(&format!(
+            "selection_bg = \"{}\"\n",
+            colors.selection_background
+        ));
+        s.push_str(&format!("ansi = {}\n", list(&colors.ansi[..8])));
+        s.push_str(&format!("brights = {}\n", list(&colors.ansi[8..])));
+        s.push_str("\n[metadata]\n");
+        s.push_str(&format!("name = {}\n", toml_string(self.theme_name())));
+        s
+    }
+
+    fn to_tmtheme(&self) -> String {
+        let colors = self.terminal_colors();
+        let entry = |key: &str, value: &str| {
+            format!("<key>{}</key><string>{}</string>", key, xml_escape(value))
+        };
+
+        let mut global = vec![
+            entry("foreground", &colors.foreground),
+            entry("background", &colors.background),
+            entry("caret", &colors.cursor),
+            entry("selection", &colors.selection_background),
+        ];
+        if let Some(bg) = self.group("CursorLine").and_then(|x| x.bg) {
+            global.push(entry("lineHighlight", &self.hex(bg)));
+        }
+        if let Some(fg) = self.group("LineNr").and_then(|x| x.fg) {
+            global.push(entry("gutterForeground", &self.hex(fg)));
+        }
+
+        let mut s = String::from(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" ",
+            "\"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n",
+            "<plist version=\"1.0\">\n",
+            "<dict>\n",
+        ));
+        s.push_str(&format!("  {}\n", entry("name", self.theme_name())));
+        s.push_str("  <key>settings</key>\n  <array>\n");
+        s.push_str(&format!(
+            "    <dict><key>settings</key><dict>{}</dict></dict>\n",
+            global.concat()
+        ));
+
+        for (name, scope) in TM_SCOPES {
+            let group = match self.group(name) {
+                Some(x) => x,
+                None => continue,
+            };
+
+            let mut settings = Vec::new();
+            if let Some(fg) = group.fg {
+                settings.push(entry("foreground", &self.hex(fg)));
+            }
+            if let Some(bg) = group.bg {
+                settings.push(entry("background", &self.hex(bg)));
+            }
+            let styles: Vec<&str> = [
+                (group.attrs.bold, "bold"),
+                (group.attrs.italic, "italic"),
+                (group.attrs.underline || group.attrs.undercurl, "underline"),
+                (group.attrs.strikethrough, "strikethrough"),
+            ]
+            .iter()
+            .filter(|(set, _)| *set)
+            .map(|(_, style)| *style)
+            .collect();
+            if !styles.is_empty() {
+                settings.push(entry("fontStyle", &styles.join(" ")));
+            }
+            if settings.is_empty() {
+                continue;
+            }
+
+            s.push_str(&format!(
+                "    <dict>{}{}<key>settings</key><dict>{}</dict></dict>\n",
+                entry("name", name),
+                entry("scope", scope),
+                settings.concat()
+            ));
+        }
+
+        s.push_str("  </array>\n</dict>\n</plist>\n");
+        s
+    }
+
+    fn to_json(&self) -> String {
+        let colors = self.terminal_colors();
+        let hex = |color: Option<Color>| match color {
+            Some(x) => Value::String(self.hex(x)),
+            None => Value::Null,
+        };
+
+        let groups: Map<String, Value> = self
+            .groups
+            .iter()
+            .map(|(name, x)| {
+                let attrs = &x.attrs;
+                let group = json!({
+                    "fg": hex(x.fg),
+                    "bg": hex(x.bg),
+                    "sp": hex(x.sp),
+                    "bold": attrs.bold,
+                    "italic": attrs.italic,
+                    "underline": attrs.underline,
+                    "undercurl": attrs.undercurl,
+                    "underdouble": attrs.underdouble,
+                    "underdotted": attrs.underdotted,
+                    "underdashed": attrs.underdashed,
+                    "strikethrough": attrs.strikethrough,
+                    "reverse": attrs.reverse,
+                    "standout": attrs.standout,
+                    "nocombine": attrs.nocombine,
+                    "link": x.link,
+                });
+                (name.clone(), group)
+            })
+            .collect();
+
+        let value = json!({
+            "name": self.theme_name(),
+            "background": self.background,
+            "foreground": colors.foreground,
+            "background_color": colors.background,
+            "cursor": colors.cursor,
+            "cursor_text": colors.cursor_text,
+            "selection_foreground": colors.selection_foreground,
+            "selection_background": colors.selection_background,
+            "terminal_colors": colors.ansi,
+            "groups": groups,
+        });
+        let mut s = serde_json::to_string_pretty(&value).unwrap_or_default();
+        s.push('\n');
+        s
+    }
+}
+
+/// Writes `s` as a basic TOML string
+fn toml_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04X}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Escapes the characters of `s` that are special within XML
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::highlight::Attributes;
+
+    fn palette() -> Palette {
+        let mut palette = Palette {
+            colorscheme: Some("test".to_string()),
+            background: "dark".to_string(),
+            termguicolors: true,
+            terminal_colors: vec![None; 16],
+            ..Default::default()
+        };
+        palette.terminal_colors[1] = Some(Color::Rgb(0xaa, 0, 0));
+
+        let group = |fg: Option<Color>, bg: Option<Color>, attrs: Attributes| Highlight {
+            fg,
+            bg,
+            attrs,
+            ..Default::default()
+        };
+        palette.groups.insert(
+            "Normal".to_string(),
+            group(
+                Some(Color::Rgb(0xee, 0xee, 0xee)),
+                Some(Color::Rgb(0x11, 0x11, 0x11)),
+                Attributes::default(),
+            ),
+        );
+        palette.groups.insert(
+            "Visual".to_string(),
+            group(
+                None,
+                None,
+                Attributes {
+                    reverse: true,
+                    ..Default::default()
+                },
+            ),
+        );
+        palette.groups.insert(
+            "Comment".to_string(),
+            group(
+                Some(Color::Indexed(81)),
+                None,
+                Attributes {
+                    italic: true,
+                    bold: true,
+                    ..Default::default()
+                },
+            ),
+        );
+        palette
+    }
+
+    #[test]
+    fn format_should_parse_ignoring_case() {
+        assert_eq!(
+            "tmTheme".parse::<ThemeFormat>().unwrap(),
+            ThemeFormat::TmTheme
+        );
+        assert_eq!(
+            "WezTerm".parse::<ThemeFormat>().unwrap(),
+            ThemeFormat::WezTerm
+        );
+        assert!("vscode".parse::<ThemeFormat>().is_err());
+    }
+
+    #[test]
+    fn kitty_should_include_primary_selection_and_terminal_colors() {
+        let s = palette().export(ThemeFormat::Kitty);
+        assert!(s.contains("\nforeground #eeeeee\n"), "{}", s);
+        assert!(s.contains("\nbackground #111111\n"), "{}", s);
+        assert!(s.contains("\ncursor #eeeeee\n"), "{}", s);
+
+        // Visual is reversed without colors of its own
+        assert!(s.contains("\nselection_foreground #111111\n"), "{}", s);
+        assert!(s.contains("\nselection_background #eeeeee\n"), "{}", s);
+
+        // Colors not defined by the colorscheme use xterm's defaults
+        assert!(s.contains("\ncolor0 #000000\n"), "{}", s);
+        assert!(s.contains("\ncolor1 #aa0000\n"), "{}", s);
+        assert!(s.contains("\ncolor15 #ffffff\n"), "{}", s);
+    }
+
+    #[test]
+    fn alacritty_should_name_terminal_colors() {
+        let s = palette().export(ThemeFormat::Alacritty);
+        assert!(
+            s.contains("[colors.primary]\nbackground = \"#111111\"\nforeground = \"#eeeeee\"\n"),
+            "{}",
+            s
+        );
+        assert!(
+            s.contains("[colors.normal]\nblack = \"#000000\"\nred = \"#aa0000\"\n"),
+            "{}",
+            s
+        );
+        assert!(
+            s.contains("[colors.bright]\nblack = \"#7f7f7f\"\n"),
+            "{}",
+            s
+        );
+    }
+
+    #[test]
+    fn wezterm_should_list_ansi_and_bright_colors() {
+        let s = palette().export(ThemeFormat::WezTerm);
+        assert!(s.contains("\nansi = [\"#000000\", \"#aa0000\", "), "{}", s);
+        assert!(s.contains("\nbrights = [\"#7f7f7f\", "), "{}", s);
+        assert!(s.contains("[metadata]\nname = \"test\"\n"), "{}", s);
+    }
+
+    #[test]
+    fn tmtheme_should_convert_groups_into_scopes() {
+        let s = palette().export(ThemeFormat::TmTheme);
+        assert!(s.starts_with("<?xml"), "{}", s);
+        assert!(
+            s.contains(concat!(
+                "<key>name</key><string>Comment</string>",
+                "<key>scope</key><string>comment</string>",
+                "<key>settings</key><dict>",
+                "<key>foreground</key><string>#5fd7ff</string>",
+                "<key>fontStyle</key><string>bold italic</string>",
+            )),
+            "{}",
+            s
+        );
+        assert!(!s.contains("<string>String</string>"), "{}", s);
+    }
+
+    #[test]
+    fn json_should_convert_colors_into_rgb() {
+        let s = palette().export(ThemeFormat::Json);
+        let value: Value = serde_json::from_str(&s).unwrap();
+        assert_eq!(value["name"], "test");
+        assert_eq!(value["terminal_colors"][1], "#aa0000");
+        assert_eq!(value["groups"]["Comment"]["fg"], "#5fd7ff");
+        assert_eq!(value["groups"]["Comment"]["bg"], Value::Null);
+        assert_eq!(value["groups"]["Comment"]["italic"], true);
+    }
+
+    #[test]
+    fn toml_string_should_escape_quotes() {
+        assert_eq!(toml_string("it's \"x\"\\"), "\"it's \\\"x\\\"\\\\\"");
+    }
+}
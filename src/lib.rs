@@ -1,15 +1,150 @@
+/// Contains types to represent highlight groups and their colors
+pub mod highlight;
+
+/// Contains functions to convert Rust values into Vimscript literals
+pub mod literal;
+
+/// Contains types to represent neovim/vim options and their values
+pub mod options;
+
 /// Contains functions to search for relevant config files
 pub mod search;
 
 /// Contains utility functions useful for neovim/vim operations
 pub mod utils;
 
+mod config;
+pub use config::{ConfigError, FieldError, VimConfig};
+
+/// Derives [`VimConfig`] for a struct whose fields declare where they are
+/// loaded from using `#[vim(...)]` attributes
+#[cfg(feature = "derive")]
+pub use vimvar_derive::VimConfig;
+
+mod de;
+mod editor;
+pub use editor::*;
+
+mod process;
+
+mod provenance;
+pub use provenance::Provenance;
+
+mod raw;
+pub use raw::*;
+
+mod value;
+pub use value::*;
+
 mod var;
 pub use var::*;
 
+/// Items used by the code generated by `#[derive(VimConfig)]`, which are not
+/// part of the public API
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::config::{ConfigLoader, ConfigSource};
+}
+
 use serde::de::DeserializeOwned;
 use serde_json::Value;
-use std::io;
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Evaluates a Vimscript expression using whatever neovim/vim instance is
+/// available in the current path alongside the default vimrc, converting the
+/// result to the specified type
+pub fn eval<T>(expr: &str) -> io::Result<T>
+where
+    T: DeserializeOwned,
+{
+    Editor::detect()?.eval(expr)
+}
+
+/// Calls a Vim function using whatever neovim/vim instance is available in the
+/// current path alongside the default vimrc, converting each element of the
+/// `args` tuple into a Vimscript literal and the return value to the
+/// specified type
+pub fn call<R>(func: &str, args: impl literal::FunctionArgs) -> io::Result<R>
+where
+    R: DeserializeOwned,
+{
+    Editor::detect()?.call(func, args)
+}
+
+/// Runs a Lua chunk using neovim (which must be available in the current path)
+/// alongside the default vimrc, converting the chunk's return value to the
+/// specified type
+pub fn eval_lua<T>(chunk: &str) -> io::Result<T>
+where
+    T: DeserializeOwned,
+{
+    let config = search::find_vimrc()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "vimrc not found"))?;
+    Editor::new(Cmd::Neovim, config).eval_lua(chunk)
+}
+
+/// Lists the variables defined within `scope` using whatever neovim/vim
+/// instance is available in the current path alongside the default vimrc,
+/// optionally limited to names starting with `prefix`
+pub fn list_vars(scope: Scope, prefix: Option<&str>) -> io::Result<Vec<VarInfo>> {
+    Editor::detect()?.list_vars(scope, prefix)
+}
+
+/// Loads every global variable whose name starts with `prefix` using whatever
+/// neovim/vim instance is available in the current path alongside the default
+/// vimrc, converting them into the specified type with `prefix` removed from
+/// each name
+///
+/// ### Examples
+///
+/// ```no_run
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// #[serde(default)]
+/// struct AleConfig {
+///     sign_error: String,
+///     lint_delay: u32,
+/// }
+///
+/// impl Default for AleConfig {
+///     fn default() -> Self {
+///         Self {
+///             sign_error: String::from(">>"),
+///             lint_delay: 200,
+///         }
+///     }
+/// }
+///
+/// let config: AleConfig = vimvar::load_prefixed("ale_").unwrap();
+/// println!("Error sign: {}", config.sign_error);
+/// ```
+pub fn load_prefixed<T>(prefix: &str) -> io::Result<T>
+where
+    T: DeserializeOwned,
+{
+    Editor::detect()?.load_prefixed(prefix)
+}
+
+/// Searches the runtimepath for files matching `pattern` using whatever
+/// neovim/vim instance is available in the current path alongside the
+/// default vimrc
+pub fn find_runtime_files(pattern: &str) -> io::Result<Vec<PathBuf>> {
+    Editor::detect()?.find_runtime_files(pattern)
+}
+
+/// Detects the filetype that whatever neovim/vim instance is available in the
+/// current path would assign to the file at `path` alongside the default
+/// vimrc, using `contents` in place of the file's contents if provided
+pub fn detect_filetype(
+    path: impl AsRef<Path>,
+    contents: Option<&str>,
+) -> io::Result<Option<String>> {
+    Editor::detect()?.detect_filetype(path, contents)
+}
 
 /// Retrieves a vim variable with `b:` scope using whatever neovim/vim
 /// instance is available in the current path
@@ -0,0 +1,609 @@
+use serde::ser::{self, Serialize};
+use std::{convert::TryFrom, fmt};
+
+/// Converts `value` into a Vimscript literal that evaluates to the same value
+/// within neovim/vim
+///
+/// ### Notes
+///
+/// * Booleans become `v:true` and `v:false`
+/// * Unit and `None` become `v:null`
+/// * Strings are double-quoted with special characters escaped
+/// * Bytes become a Blob (`0z...`)
+/// * Sequences and tuples become Lists, while maps and structs become Dicts
+/// * Enum variants follow the externally tagged representation used by
+///   `serde_json`, meaning unit variants become strings and all others become
+///   a Dict with a single key of the variant's name
+/// * Non-finite floats are produced via `str2float()`
+///
+/// ### Examples
+///
+/// ```
+/// use std::collections::BTreeMap;
+///
+/// assert_eq!(vimvar::literal::to_string(&true).unwrap(), "v:true");
+/// assert_eq!(vimvar::literal::to_string(&1.5).unwrap(), "1.5");
+/// assert_eq!(vimvar::literal::to_string("say \"hi\"").unwrap(), r#""say \"hi\"""#);
+/// assert_eq!(vimvar::literal::to_string(&(1, None::<u8>)).unwrap(), "[1, v:null]");
+///
+/// let mut map = BTreeMap::new();
+/// map.insert("width", 80);
+/// assert_eq!(vimvar::literal::to_string(&map).unwrap(), r#"{"width": 80}"#);
+/// ```
+pub fn to_string<T>(value: &T) -> Result<String, Error>
+where
+    T: Serialize + ?Sized,
+{
+    let mut serializer = Serializer {
+        output: String::new(),
+    };
+    value.serialize(&mut serializer)?;
+    Ok(serializer.output)
+}
+
+/// Represents the arguments of a function called with [`crate::Editor::call`],
+/// implemented for tuples of up to twelve serializable values where each
+/// element is a separate argument
+///
+/// ### Examples
+///
+/// ```
+/// use vimvar::literal::FunctionArgs;
+///
+/// assert_eq!(().to_list().unwrap(), "[]");
+/// assert_eq!((None::<u8>,).to_list().unwrap(), "[v:null]");
+/// assert_eq!((vec![1, 2, 3],).to_list().unwrap(), "[[1, 2, 3]]");
+/// assert_eq!((1, "two").to_list().unwrap(), r#"[1, "two"]"#);
+/// ```
+pub trait FunctionArgs {
+    /// Converts the arguments into a Vimscript List literal
+    fn to_list(&self) -> Result<String, Error>;
+}
+
+impl FunctionArgs for () {
+    fn to_list(&self) -> Result<String, Error> {
+        Ok(String::from("[]"))
+    }
+}
+
+impl<T: FunctionArgs + ?Sized> FunctionArgs for &T {
+    fn to_list(&self) -> Result<String, Error> {
+        (**self).to_list()
+    }
+}
+
+macro_rules! impl_function_args {
+    ($($name:ident)+) => {
+        impl<$($name: Serialize),+> FunctionArgs for ($($name,)+) {
+            fn to_list(&self) -> Result<String, Error> {
+                // Tuples of any length (including one) become Lists
+                to_string(self)
+            }
+        }
+    };
+}
+
+impl_function_args!(A);
+impl_function_args!(A B);
+impl_function_args!(A B C);
+impl_function_args!(A B C D);
+impl_function_args!(A B C D E);
+impl_function_args!(A B C D E F);
+impl_function_args!(A B C D E F G);
+impl_function_args!(A B C D E F G H);
+impl_function_args!(A B C D E F G H I);
+impl_function_args!(A B C D E F G H I J);
+impl_function_args!(A B C D E F G H I J K);
+impl_function_args!(A B C D E F G H I J K L);
+
+/// Represents an error encountered while converting into a Vimscript literal
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+/// Serializer that writes Vimscript literals into a string
+struct Serializer {
+    output: String,
+}
+
+impl Serializer {
+    fn write_str_literal(&mut self, s: &str) {
+        self.output.push('"');
+        for c in s.chars() {
+            match c {
+                '"' => self.output.push_str("\\\""),
+                '\\' => self.output.push_str("\\\\"),
+                '\n' => self.output.push_str("\\n"),
+                '\r' => self.output.push_str("\\r"),
+                '\t' => self.output.push_str("\\t"),
+                c if c.is_control() => self.output.push_str(&format!("\\u{:04x}", c as u32)),
+                c => self.output.push(c),
+            }
+        }
+        self.output.push('"');
+    }
+
+    fn write_float(&mut self, v: f64) {
+        if v.is_nan() {
+            self.output.push_str("str2float('nan')");
+        } else if v.is_infinite() && v > 0.0 {
+            self.output.push_str("str2float('inf')");
+        } else if v.is_infinite() {
+            self.output.push_str("str2float('-inf')");
+        } else {
+            // NOTE: Vimscript requires a float literal to have digits on both
+            //       sides of the decimal point, including before an exponent
+            let s = format!("{:?}", v);
+            match s.split_once('e') {
+                Some((mantissa, exp)) if !mantissa.contains('.') => {
+                    self.output.push_str(&format!("{}.0e{}", mantissa, exp))
+                }
+                _ => self.output.push_str(&s),
+            }
+        }
+    }
+}
+
+impl<'a> ser::Serializer for &'a mut Serializer {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = Compound<'a>;
+    type SerializeMap = Compound<'a>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = Compound<'a>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        self.output.push_str(if v { "v:true" } else { "v:false" });
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        self.output.push_str(&v.to_string());
+        Ok(())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        // Numbers in neovim/vim are signed 64-bit integers
+        let v = i64::try_from(v)
+            .map_err(|_| Error(format!("{} is too large for a Vimscript Number", v)))?;
+        self.serialize_i64(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        self.write_float(f64::from(v));
+        Ok(())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        self.write_float(v);
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        self.write_str_literal(v.encode_utf8(&mut [0; 4]));
+        Ok(())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        self.write_str_literal(v);
+        Ok(())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        self.output.push_str("0z");
+        for byte in v {
+            self.output.push_str(&format!("{:02X}", byte));
+        }
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        self.output.push_str("v:null");
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.output.push('{');
+        self.write_str_literal(variant);
+        self.output.push_str(": ");
+        value.serialize(&mut *self)?;
+        self.output.push('}');
+        Ok(())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        self.output.push('[');
+        Ok(Compound::new(self, "]"))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        self.output.push('{');
+        self.write_str_literal(variant);
+        self.output.push_str(": [");
+        Ok(Compound::new(self, "]}"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        self.output.push('{');
+        Ok(Compound::new(self, "}"))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        self.output.push('{');
+        self.write_str_literal(variant);
+        self.output.push_str(": {");
+        Ok(Compound::new(self, "}}"))
+    }
+}
+
+/// Serializer for Lists and Dicts, writing separators between elements and
+/// the closing delimiter upon completion
+struct Compound<'a> {
+    ser: &'a mut Serializer,
+    end: &'static str,
+    first: bool,
+}
+
+impl<'a> Compound<'a> {
+    fn new(ser: &'a mut Serializer, end: &'static str) -> Self {
+        Self {
+            ser,
+            end,
+            first: true,
+        }
+    }
+
+    fn write_separator(&mut self) {
+        if !self.first {
+            self.ser.output.push_str(", ");
+        }
+        self.first = false;
+    }
+
+    fn write_entry<T>(&mut self, key: &str, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.write_separator();
+        self.ser.write_str_literal(key);
+        self.ser.output.push_str(": ");
+        value.serialize(&mut *self.ser)
+    }
+
+    fn finish(self) -> Result<(), Error> {
+        self.ser.output.push_str(self.end);
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.write_separator();
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeMap for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        // Dict keys are always strings, so we convert other simple keys into
+        // strings the same way that neovim/vim would
+        let key = match serde_json::to_value(key) {
+            Ok(serde_json::Value::String(key)) => key,
+            Ok(serde_json::Value::Number(key)) => key.to_string(),
+            Ok(serde_json::Value::Bool(key)) => key.to_string(),
+            _ => return Err(Error(String::from("Dict keys must be strings"))),
+        };
+
+        self.write_separator();
+        self.ser.write_str_literal(&key);
+        self.ser.output.push_str(": ");
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.write_entry(key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.write_entry(key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn to_string_should_escape_special_characters_in_strings() {
+        assert_eq!(
+            to_string("a\"b\\c\nd\te'f\u{1}").unwrap(),
+            r#""a\"b\\c\nd\te'f\u0001""#
+        );
+    }
+
+    #[test]
+    fn to_string_should_support_floats() {
+        assert_eq!(to_string(&1.0).unwrap(), "1.0");
+        assert_eq!(to_string(&-0.25).unwrap(), "-0.25");
+        assert_eq!(to_string(&1e100).unwrap(), "1.0e100");
+        assert_eq!(to_string(&1.5e-10).unwrap(), "1.5e-10");
+        assert_eq!(to_string(&f64::INFINITY).unwrap(), "str2float('inf')");
+        assert_eq!(to_string(&f64::NEG_INFINITY).unwrap(), "str2float('-inf')");
+        assert_eq!(to_string(&f64::NAN).unwrap(), "str2float('nan')");
+    }
+
+    #[test]
+    fn to_string_should_fail_for_numbers_too_large() {
+        assert!(to_string(&u64::MAX).is_err());
+    }
+
+    #[test]
+    fn to_string_should_support_bytes_as_blob() {
+        assert_eq!(to_string(&Bytes(&[0, 15, 255])).unwrap(), "0z000FFF");
+    }
+
+    #[test]
+    fn to_string_should_support_nested_collections() {
+        let mut map = BTreeMap::new();
+        map.insert(1, vec![Some("a"), None]);
+        map.insert(2, vec![]);
+
+        assert_eq!(to_string(&map).unwrap(), r#"{"1": ["a", v:null], "2": []}"#);
+    }
+
+    #[test]
+    fn to_string_should_fail_for_non_string_keys() {
+        let mut map = BTreeMap::new();
+        map.insert(vec![1], 1);
+
+        assert!(to_string(&map).is_err());
+    }
+
+    #[test]
+    fn to_string_should_externally_tag_enums() {
+        #[derive(serde::Serialize)]
+        enum Example {
+            Unit,
+            Newtype(u8),
+            Tuple(u8, u8),
+            Struct { a: u8 },
+        }
+
+        assert_eq!(to_string(&Example::Unit).unwrap(), r#""Unit""#);
+        assert_eq!(
+            to_string(&Example::Newtype(1)).unwrap(),
+            r#"{"Newtype": 1}"#
+        );
+        assert_eq!(
+            to_string(&Example::Tuple(1, 2)).unwrap(),
+            r#"{"Tuple": [1, 2]}"#
+        );
+        assert_eq!(
+            to_string(&Example::Struct { a: 1 }).unwrap(),
+            r#"{"Struct": {"a": 1}}"#
+        );
+    }
+
+    /// Wraps a byte slice such that it serializes as bytes rather than as a
+    /// sequence of numbers
+    struct Bytes<'a>(&'a [u8]);
+
+    impl Serialize for Bytes<'_> {
+        fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_bytes(self.0)
+        }
+    }
+}
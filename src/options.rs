@@ -0,0 +1,385 @@
+use serde::Deserialize;
+use serde_json::Value;
+use std::{convert::TryFrom, fmt, io, str::FromStr};
+
+mod char_class;
+pub use char_class::*;
+
+mod chars;
+pub use chars::*;
+
+mod comma_list;
+pub use comma_list::*;
+
+mod flags;
+pub use flags::*;
+
+mod key_value;
+pub use key_value::*;
+
+/// Represents which value of an option to load, as options with a local value
+/// (such as `'shiftwidth'`) also have a global value used for new buffers and
+/// windows
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum OptionScope {
+    /// Value in effect for the current buffer or window (`&opt`)
+    Effective,
+
+    /// Local value for the current buffer or window (`&l:opt`)
+    Local,
+
+    /// Global value (`&g:opt`)
+    Global,
+}
+
+impl Default for OptionScope {
+    /// Returns effective as default
+    fn default() -> Self {
+        Self::Effective
+    }
+}
+
+impl OptionScope {
+    /// Converts to a str representing the prefix used to reference an option
+    ///
+    /// ### Examples
+    ///
+    /// ```
+    /// use vimvar::options::OptionScope;
+    ///
+    /// assert_eq!(OptionScope::Effective.as_str(), "&");
+    /// assert_eq!(OptionScope::Local.as_str(), "&l:");
+    /// assert_eq!(OptionScope::Global.as_str(), "&g:");
+    /// ```
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Effective => "&",
+            Self::Local => "&l:",
+            Self::Global => "&g:",
+        }
+    }
+}
+
+impl fmt::Display for OptionScope {
+    /// Writes scope using the [`Self::as_str`] representation
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Represents the type of an option's value
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OptionType {
+    Boolean,
+    Number,
+    String,
+}
+
+impl OptionType {
+    /// Converts to a str representing the type
+    ///
+    /// ### Examples
+    ///
+    /// ```
+    /// use vimvar::options::OptionType;
+    ///
+    /// assert_eq!(OptionType::Boolean.as_str(), "boolean");
+    /// assert_eq!(OptionType::Number.as_str(), "number");
+    /// assert_eq!(OptionType::String.as_str(), "string");
+    /// ```
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Boolean => "boolean",
+            Self::Number => "number",
+            Self::String => "string",
+        }
+    }
+}
+
+impl fmt::Display for OptionType {
+    /// Writes type using the [`Self::as_str`] representation
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Represents what an option's local value belongs to
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Deserialize)]
+pub enum OptionOwner {
+    /// Option only has a global value (`'clipboard'`)
+    #[serde(rename = "global")]
+    Global,
+
+    /// Option is local to each window (`'number'`)
+    #[serde(rename = "win")]
+    Window,
+
+    /// Option is local to each buffer (`'shiftwidth'`)
+    #[serde(rename = "buf")]
+    Buffer,
+}
+
+/// Represents the value of an option
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OptionValue {
+    Bool(bool),
+    Number(i64),
+    String(String),
+}
+
+impl OptionValue {
+    /// Returns the type of the value
+    pub fn kind(&self) -> OptionType {
+        match self {
+            Self::Bool(_) => OptionType::Boolean,
+            Self::Number(_) => OptionType::Number,
+            Self::String(_) => OptionType::String,
+        }
+    }
+
+    /// Returns the value if it is a boolean
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    /// Returns the value if it is a number
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::Number(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    /// Returns the value if it is a string
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    /// Parses a string value into a structured type such as [`ListChars`] or
+    /// [`CommaList`], returning an [`io::Error`] if the value is not a string
+    /// or fails to parse
+    ///
+    /// ### Examples
+    ///
+    /// ```
+    /// use vimvar::options::{Flags, OptionValue};
+    ///
+    /// let value = OptionValue::String(String::from("tcqj"));
+    /// let flags: Flags = value.parse().unwrap();
+    /// assert!(flags.contains('q'));
+    /// ```
+    pub fn parse<T>(&self) -> io::Result<T>
+    where
+        T: FromStr<Err = io::Error>,
+    {
+        match self {
+            Self::String(x) => x.parse(),
+            x => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Expected string option value, but got {}", x.kind()),
+            )),
+        }
+    }
+
+    /// Creates a value of type `kind` from the JSON produced by the editor,
+    /// where booleans may be represented as either booleans or numbers
+    pub(crate) fn from_json(kind: OptionType, value: Value) -> io::Result<Self> {
+        match (kind, value) {
+            (OptionType::Boolean, Value::Bool(x)) => Ok(Self::Bool(x)),
+            (OptionType::Boolean, Value::Number(x)) => Ok(Self::Bool(x.as_i64() != Some(0))),
+            (OptionType::Number, Value::Number(x)) => {
+                x.as_i64().map(Self::Number).ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidData, format!("Invalid number {}", x))
+                })
+            }
+            (OptionType::String, Value::String(x)) => Ok(Self::String(x)),
+            (kind, value) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Expected {} option value, but got {}", kind, value),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for OptionValue {
+    /// Writes the value as it would appear after `=` within `:set`, where
+    /// booleans are written as `true` or `false`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bool(x) => write!(f, "{}", x),
+            Self::Number(x) => write!(f, "{}", x),
+            Self::String(x) => write!(f, "{}", x),
+        }
+    }
+}
+
+/// Represents an option alongside its current value and metadata
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OptionInfo {
+    /// Full name of the option (`shiftwidth`)
+    pub name: String,
+
+    /// Abbreviated name of the option (`sw`), only available with neovim
+    pub short_name: Option<String>,
+
+    /// Type of the option's value
+    pub kind: OptionType,
+
+    /// Value of the option in effect for the current buffer and window
+    pub value: OptionValue,
+
+    /// Default value of the option, only available with neovim
+    pub default: Option<OptionValue>,
+
+    /// What the option's local value belongs to, only available with neovim
+    pub owner: Option<OptionOwner>,
+
+    /// Whether the option is global but can also have a local value (such as
+    /// `'undolevels'`), only available with neovim
+    pub global_local: Option<bool>,
+
+    /// Whether the option has been set, only available with neovim
+    pub was_set: Option<bool>,
+}
+
+/// Represents the value of an option as reported by `s:option()`
+#[derive(Deserialize)]
+pub(crate) struct RawOption {
+    #[serde(rename = "type")]
+    kind: OptionType,
+    value: Value,
+}
+
+impl RawOption {
+    /// Converts into a value of the reported type
+    pub(crate) fn into_value(self) -> io::Result<OptionValue> {
+        OptionValue::from_json(self.kind, self.value)
+    }
+}
+
+/// Represents an option as reported by the editor, where the metadata is only
+/// populated by `nvim_get_all_options_info()`
+#[derive(Deserialize)]
+pub(crate) struct RawOptionInfo {
+    name: String,
+    #[serde(default)]
+    shortname: String,
+    #[serde(rename = "type")]
+    kind: OptionType,
+    value: Value,
+    default: Option<Value>,
+    scope: Option<OptionOwner>,
+    global_local: Option<bool>,
+    was_set: Option<bool>,
+}
+
+impl TryFrom<RawOptionInfo> for OptionInfo {
+    type Error = io::Error;
+
+    fn try_from(raw: RawOptionInfo) -> io::Result<Self> {
+        let kind = raw.kind;
+        Ok(Self {
+            value: OptionValue::from_json(kind, raw.value)?,
+            default: raw
+                .default
+                .map(|x| OptionValue::from_json(kind, x))
+                .transpose()?,
+            short_name: Some(raw.shortname).filter(|x| !x.is_empty()),
+            name: raw.name,
+            kind,
+            owner: raw.scope,
+            global_local: raw.global_local,
+            was_set: raw.was_set,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn option_value_should_convert_numbers_into_booleans() {
+        assert_eq!(
+            OptionValue::from_json(OptionType::Boolean, json!(1)).unwrap(),
+            OptionValue::Bool(true)
+        );
+        assert_eq!(
+            OptionValue::from_json(OptionType::Boolean, json!(0)).unwrap(),
+            OptionValue::Bool(false)
+        );
+        assert_eq!(
+            OptionValue::from_json(OptionType::Boolean, json!(true)).unwrap(),
+            OptionValue::Bool(true)
+        );
+    }
+
+    #[test]
+    fn option_value_should_fail_if_json_does_not_match_type() {
+        assert!(OptionValue::from_json(OptionType::Number, json!("8")).is_err());
+        assert!(OptionValue::from_json(OptionType::String, json!(8)).is_err());
+        assert!(OptionValue::from_json(OptionType::Boolean, json!("yes")).is_err());
+    }
+
+    #[test]
+    fn option_info_should_support_nvim_metadata() {
+        let raw: RawOptionInfo = serde_json::from_value(json!({
+            "name": "expandtab",
+            "shortname": "et",
+            "type": "boolean",
+            "value": 1,
+            "default": false,
+            "scope": "buf",
+            "global_local": false,
+            "was_set": true,
+            "last_set_sid": 0,
+        }))
+        .unwrap();
+
+        assert_eq!(
+            OptionInfo::try_from(raw).unwrap(),
+            OptionInfo {
+                name: "expandtab".to_string(),
+                short_name: Some("et".to_string()),
+                kind: OptionType::Boolean,
+                value: OptionValue::Bool(true),
+                default: Some(OptionValue::Bool(false)),
+                owner: Some(OptionOwner::Buffer),
+                global_local: Some(false),
+                was_set: Some(true),
+            }
+        );
+    }
+
+    #[test]
+    fn option_info_should_support_missing_metadata() {
+        let raw: RawOptionInfo = serde_json::from_value(json!({
+            "name": "shiftwidth",
+            "type": "number",
+            "value": 8,
+        }))
+        .unwrap();
+
+        assert_eq!(
+            OptionInfo::try_from(raw).unwrap(),
+            OptionInfo {
+                name: "shiftwidth".to_string(),
+                short_name: None,
+                kind: OptionType::Number,
+                value: OptionValue::Number(8),
+                default: None,
+                owner: None,
+                global_local: None,
+                was_set: None,
+            }
+        );
+    }
+}
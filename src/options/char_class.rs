@@ -0,0 +1,264 @@
+use std::{fmt, io, str::FromStr};
+
+/// Specification of which characters belong to a class, used by options such
+/// as `'iskeyword'`, `'isfname'`, `'isident'` and `'isprint'`
+///
+/// ### Examples
+///
+/// ```
+/// use vimvar::options::CharClass;
+///
+/// let iskeyword: CharClass = "@,48-57,_,192-255,^e".parse().unwrap();
+/// assert!(iskeyword.contains('a'));
+/// assert!(iskeyword.contains('7'));
+/// assert!(iskeyword.contains('_'));
+/// assert!(!iskeyword.contains('e'));
+/// assert!(!iskeyword.contains('-'));
+/// assert_eq!(iskeyword.to_string(), "@,48-57,_,192-255,^e");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CharClass(Vec<CharClassItem>);
+
+impl CharClass {
+    /// Creates a new class from `items`
+    pub fn new(items: Vec<CharClassItem>) -> Self {
+        Self(items)
+    }
+
+    /// Returns the items of the class in order
+    pub fn items(&self) -> &[CharClassItem] {
+        &self.0
+    }
+
+    /// Returns true if `c` belongs to the class, where later items take
+    /// precedence over earlier ones
+    ///
+    /// ### Notes
+    ///
+    /// * Only the specification itself is considered, whereas the editor
+    ///   also treats some characters above 255 specially (such as counting
+    ///   most multibyte letters as keyword characters)
+    pub fn contains(&self, c: char) -> bool {
+        self.0.iter().fold(false, |contains, item| {
+            if item.range.contains(c) {
+                !item.exclude
+            } else {
+                contains
+            }
+        })
+    }
+}
+
+/// Represents a single comma-separated item within a [`CharClass`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CharClassItem {
+    /// Whether the characters are removed from the class (`^x`) rather than
+    /// added to it
+    pub exclude: bool,
+
+    /// Characters covered by the item
+    pub range: CharRange,
+}
+
+/// Represents the characters covered by a [`CharClassItem`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CharRange {
+    /// Every alphabetic character (`@`)
+    Alpha,
+
+    /// A single character (`_` or `95`)
+    Single(CharSpec),
+
+    /// An inclusive range of characters (`48-57` or `a-z`)
+    Range(CharSpec, CharSpec),
+}
+
+impl CharRange {
+    /// Returns true if `c` is covered by the range
+    pub fn contains(&self, c: char) -> bool {
+        match self {
+            Self::Alpha => c.is_alphabetic(),
+            Self::Single(x) => x.code() == c as u32,
+            Self::Range(start, end) => (start.code()..=end.code()).contains(&(c as u32)),
+        }
+    }
+}
+
+/// Represents how a character is written within a [`CharClassItem`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CharSpec {
+    /// Character written as its decimal code (`95`)
+    Code(u32),
+
+    /// Character written as itself (`_`)
+    Char(char),
+}
+
+impl CharSpec {
+    /// Returns the code of the character
+    pub fn code(&self) -> u32 {
+        match self {
+            Self::Code(x) => *x,
+            Self::Char(x) => *x as u32,
+        }
+    }
+}
+
+impl fmt::Display for CharSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Code(x) => write!(f, "{}", x),
+            Self::Char(x) => write!(f, "{}", x),
+        }
+    }
+}
+
+impl FromStr for CharClass {
+    type Err = io::Error;
+
+    /// Parses the class the same way the editor does, where each item is
+    /// optionally prefixed with `^`, digits are read as character codes and a
+    /// lone `@` stands for every alphabetic character (`@-@` being `@` itself)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |msg: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid character class \"{}\": {}", s, msg),
+            )
+        };
+
+        let mut items = Vec::new();
+        let mut chars = s.chars().peekable();
+        while chars.peek().is_some() {
+            let mut exclude = false;
+            if chars.peek() == Some(&'^') {
+                chars.next();
+                if chars.peek().is_some() {
+                    exclude = true;
+                } else {
+                    items.push(CharClassItem {
+                        exclude,
+                        range: CharRange::Single(CharSpec::Char('^')),
+                    });
+                    break;
+                }
+            }
+
+            let start = read_char_spec(&mut chars).ok_or_else(|| invalid("missing character"))?;
+            let end = if chars.peek() == Some(&'-') {
+                chars.next();
+                Some(read_char_spec(&mut chars).ok_or_else(|| invalid("missing end of range"))?)
+            } else {
+                None
+            };
+
+            let range = match (start, end) {
+                (CharSpec::Char('@'), None) => CharRange::Alpha,
+                (start, None) => CharRange::Single(start),
+                (start, Some(end)) => CharRange::Range(start, end),
+            };
+            items.push(CharClassItem { exclude, range });
+
+            match chars.next() {
+                None | Some(',') => {}
+                Some(c) => return Err(invalid(&format!("unexpected '{}'", c))),
+            }
+        }
+
+        Ok(Self(items))
+    }
+}
+
+/// Reads either a decimal character code or a single character
+fn read_char_spec(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> Option<CharSpec> {
+    let mut digits = String::new();
+    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+        digits.push(c);
+    }
+
+    if digits.is_empty() {
+        chars.next().map(CharSpec::Char)
+    } else {
+        digits.parse().ok().map(CharSpec::Code)
+    }
+}
+
+impl fmt::Display for CharClass {
+    /// Writes the class as it would appear within the option's value
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, item) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            if item.exclude {
+                write!(f, "^")?;
+            }
+            match item.range {
+                CharRange::Alpha => write!(f, "@")?,
+
+                // A lone @ would be read as every alphabetic character
+                CharRange::Single(CharSpec::Char('@')) => write!(f, "@-@")?,
+                CharRange::Single(x) => write!(f, "{}", x)?,
+                CharRange::Range(start, end) => write!(f, "{}-{}", start, end)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(exclude: bool, range: CharRange) -> CharClassItem {
+        CharClassItem { exclude, range }
+    }
+
+    #[test]
+    fn should_parse_alpha_codes_chars_and_ranges() {
+        let class: CharClass = "@,48-57,_,a-z,@-@,^x".parse().unwrap();
+        assert_eq!(
+            class.items(),
+            [
+                item(false, CharRange::Alpha),
+                item(
+                    false,
+                    CharRange::Range(CharSpec::Code(48), CharSpec::Code(57))
+                ),
+                item(false, CharRange::Single(CharSpec::Char('_'))),
+                item(
+                    false,
+                    CharRange::Range(CharSpec::Char('a'), CharSpec::Char('z'))
+                ),
+                item(
+                    false,
+                    CharRange::Range(CharSpec::Char('@'), CharSpec::Char('@'))
+                ),
+                item(true, CharRange::Single(CharSpec::Char('x'))),
+            ]
+        );
+        assert_eq!(class.to_string(), "@,48-57,_,a-z,@-@,^x");
+    }
+
+    #[test]
+    fn should_treat_trailing_caret_as_character() {
+        let class: CharClass = "a,^".parse().unwrap();
+        assert!(class.contains('^'));
+        assert_eq!(class.to_string(), "a,^");
+    }
+
+    #[test]
+    fn should_write_lone_at_sign_as_range() {
+        let class = CharClass::new(vec![item(false, CharRange::Single(CharSpec::Char('@')))]);
+        assert_eq!(class.to_string(), "@-@");
+        assert!(class.contains('@'));
+        assert!(!class.contains('a'));
+    }
+
+    #[test]
+    fn should_fail_if_invalid() {
+        assert!("a-".parse::<CharClass>().is_err());
+        assert!("ab".parse::<CharClass>().is_err());
+        assert!("48-57x".parse::<CharClass>().is_err());
+    }
+}
@@ -0,0 +1,348 @@
+use std::{fmt, io, str::FromStr};
+
+/// Characters used to display whitespace and other special characters when
+/// `'list'` is enabled, parsed from `'listchars'`
+///
+/// ### Examples
+///
+/// ```
+/// use vimvar::options::ListChars;
+///
+/// let lcs: ListChars = "tab:> ,trail:-,nbsp:+".parse().unwrap();
+/// assert_eq!(lcs.tab.as_deref(), Some("> "));
+/// assert_eq!(lcs.trail, Some('-'));
+/// assert_eq!(lcs.eol, None);
+/// assert_eq!(lcs.to_string(), "tab:> ,trail:-,nbsp:+");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ListChars {
+    /// Character shown at the end of each line
+    pub eol: Option<char>,
+
+    /// Two or three characters used to show a tab
+    pub tab: Option<String>,
+
+    /// Character shown for a space
+    pub space: Option<char>,
+
+    /// Characters repeated to show a sequence of spaces
+    pub multispace: Option<String>,
+
+    /// Character shown for leading spaces
+    pub lead: Option<char>,
+
+    /// Characters repeated to show a sequence of leading spaces
+    pub leadmultispace: Option<String>,
+
+    /// Character shown for trailing spaces
+    pub trail: Option<char>,
+
+    /// Character shown in the last column when a line continues beyond the
+    /// window
+    pub extends: Option<char>,
+
+    /// Character shown in the first column when a line continues before the
+    /// window
+    pub precedes: Option<char>,
+
+    /// Character shown in place of concealed text
+    pub conceal: Option<char>,
+
+    /// Character shown for a non-breakable space
+    pub nbsp: Option<char>,
+
+    /// Items not recognized by this crate, in the order they appear
+    pub other: Vec<(String, String)>,
+}
+
+impl FromStr for ListChars {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lcs = Self::default();
+
+        for (key, value) in parse_char_items(s, "listchars")? {
+            match key.as_str() {
+                "eol" => lcs.eol = first_char(&value),
+                "tab" => lcs.tab = Some(value),
+                "space" => lcs.space = first_char(&value),
+                "multispace" => lcs.multispace = Some(value),
+                "lead" => lcs.lead = first_char(&value),
+                "leadmultispace" => lcs.leadmultispace = Some(value),
+                "trail" => lcs.trail = first_char(&value),
+                "extends" => lcs.extends = first_char(&value),
+                "precedes" => lcs.precedes = first_char(&value),
+                "conceal" => lcs.conceal = first_char(&value),
+                "nbsp" => lcs.nbsp = first_char(&value),
+                _ => lcs.other.push((key, value)),
+            }
+        }
+
+        Ok(lcs)
+    }
+}
+
+impl fmt::Display for ListChars {
+    /// Writes the characters as they would appear within `'listchars'`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let items = [
+            ("eol", self.eol.map(String::from)),
+            ("tab", self.tab.clone()),
+            ("space", self.space.map(String::from)),
+            ("multispace", self.multispace.clone()),
+            ("lead", self.lead.map(String::from)),
+            ("leadmultispace", self.leadmultispace.clone()),
+            ("trail", self.trail.map(String::from)),
+            ("extends", self.extends.map(String::from)),
+            ("precedes", self.precedes.map(String::from)),
+            ("conceal", self.conceal.map(String::from)),
+            ("nbsp", self.nbsp.map(String::from)),
+        ];
+
+        write_char_items(f, &items, &self.other)
+    }
+}
+
+/// Characters used to fill the statuslines, vertical separators and other
+/// parts of the screen, parsed from `'fillchars'`
+///
+/// ### Examples
+///
+/// ```
+/// use vimvar::options::FillChars;
+///
+/// let fcs: FillChars = "vert:│,fold:-,eob: ".parse().unwrap();
+/// assert_eq!(fcs.vert, Some('│'));
+/// assert_eq!(fcs.eob, Some(' '));
+/// assert_eq!(fcs.to_string(), "vert:│,fold:-,eob: ");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FillChars {
+    /// Character filling the statusline of the current window
+    pub stl: Option<char>,
+
+    /// Character filling the statusline of other windows
+    pub stlnc: Option<char>,
+
+    /// Character filling the window bar
+    pub wbr: Option<char>,
+
+    /// Character used for vertical separators
+    pub vert: Option<char>,
+
+    /// Character filling the text of closed folds
+    pub fold: Option<char>,
+
+    /// Character marking the start of an open fold
+    pub foldopen: Option<char>,
+
+    /// Character marking a closed fold
+    pub foldclose: Option<char>,
+
+    /// Character marking the middle of an open fold
+    pub foldsep: Option<char>,
+
+    /// Character shown for deleted lines when diffing
+    pub diff: Option<char>,
+
+    /// Character shown for empty lines after the end of the buffer
+    pub eob: Option<char>,
+
+    /// Character shown in place of a last line that does not fit
+    pub lastline: Option<char>,
+
+    /// Items not recognized by this crate, in the order they appear
+    pub other: Vec<(String, String)>,
+}
+
+impl FromStr for FillChars {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fcs = Self::default();
+
+        for (key, value) in parse_char_items(s, "fillchars")? {
+            match key.as_str() {
+                "stl" => fcs.stl = first_char(&value),
+                "stlnc" => fcs.stlnc = first_char(&value),
+                "wbr" => fcs.wbr = first_char(&value),
+                "vert" => fcs.vert = first_char(&value),
+                "fold" => fcs.fold = first_char(&value),
+                "foldopen" => fcs.foldopen = first_char(&value),
+                "foldclose" => fcs.foldclose = first_char(&value),
+                "foldsep" => fcs.foldsep = first_char(&value),
+                "diff" => fcs.diff = first_char(&value),
+                "eob" => fcs.eob = first_char(&value),
+                "lastline" => fcs.lastline = first_char(&value),
+                _ => fcs.other.push((key, value)),
+            }
+        }
+
+        Ok(fcs)
+    }
+}
+
+impl fmt::Display for FillChars {
+    /// Writes the characters as they would appear within `'fillchars'`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let items = [
+            ("stl", self.stl),
+            ("stlnc", self.stlnc),
+            ("wbr", self.wbr),
+            ("vert", self.vert),
+            ("fold", self.fold),
+            ("foldopen", self.foldopen),
+            ("foldclose", self.foldclose),
+            ("foldsep", self.foldsep),
+            ("diff", self.diff),
+            ("eob", self.eob),
+            ("lastline", self.lastline),
+        ];
+        let items: Vec<_> = items
+            .iter()
+            .map(|(key, c)| (*key, c.map(String::from)))
+            .collect();
+
+        write_char_items(f, &items, &self.other)
+    }
+}
+
+fn first_char(value: &str) -> Option<char> {
+    value.chars().next()
+}
+
+/// Splits the `key:chars` items of `'listchars'` or `'fillchars'` the same way
+/// the editor does, reading values by character so that a comma or colon can
+/// itself be used as a value
+///
+/// ### Notes
+///
+/// * `tab` takes two characters and an optional third
+/// * `multispace` and `leadmultispace` take every character up to the next
+///   comma
+/// * Everything else takes exactly one character
+fn parse_char_items(s: &str, option: &str) -> io::Result<Vec<(String, String)>> {
+    let invalid = |msg: String| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid '{}' value \"{}\": {}", option, s, msg),
+        )
+    };
+
+    let mut items = Vec::new();
+    let mut rest = s;
+    while !rest.is_empty() {
+        let (key, tail) = rest
+            .split_once(':')
+            .ok_or_else(|| invalid(format!("missing ':' within \"{}\"", rest)))?;
+
+        let mut chars = tail.chars().peekable();
+        let mut value = String::new();
+        match key {
+            "tab" => {
+                value.extend(chars.next());
+                value.extend(chars.next());
+                if let Some(c) = chars.next_if(|c| *c != ',') {
+                    value.push(c);
+                }
+                if value.chars().count() < 2 {
+                    return Err(invalid(String::from("tab requires two characters")));
+                }
+            }
+            "multispace" | "leadmultispace" => {
+                while let Some(c) = chars.next_if(|c| *c != ',') {
+                    value.push(c);
+                }
+            }
+            _ => value.extend(chars.next()),
+        }
+        if value.is_empty() {
+            return Err(invalid(format!("missing character for {}", key)));
+        }
+
+        rest = &tail[value.len()..];
+        if !rest.is_empty() {
+            rest = rest
+                .strip_prefix(',')
+                .ok_or_else(|| invalid(format!("expected ',' after {}", key)))?;
+        }
+        items.push((key.to_string(), value));
+    }
+
+    Ok(items)
+}
+
+fn write_char_items(
+    f: &mut fmt::Formatter<'_>,
+    items: &[(&str, Option<String>)],
+    other: &[(String, String)],
+) -> fmt::Result {
+    let items = items
+        .iter()
+        .filter_map(|(key, value)| value.as_deref().map(|value| (*key, value)))
+        .chain(
+            other
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_str())),
+        );
+
+    for (i, (key, value)) in items.enumerate() {
+        if i > 0 {
+            write!(f, ",")?;
+        }
+        write!(f, "{}:{}", key, value)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_chars_should_support_commas_and_colons_as_values() {
+        let lcs: ListChars = "tab:,:,trail:,,nbsp::".parse().unwrap();
+        assert_eq!(lcs.tab.as_deref(), Some(",:"));
+        assert_eq!(lcs.trail, Some(','));
+        assert_eq!(lcs.nbsp, Some(':'));
+        assert_eq!(lcs.to_string(), "tab:,:,trail:,,nbsp::");
+    }
+
+    #[test]
+    fn list_chars_should_support_three_character_tabs_and_multispace() {
+        let lcs: ListChars = "tab:<->,multispace:---+,eol:↲".parse().unwrap();
+        assert_eq!(lcs.tab.as_deref(), Some("<->"));
+        assert_eq!(lcs.multispace.as_deref(), Some("---+"));
+        assert_eq!(lcs.eol, Some('↲'));
+        assert_eq!(lcs.to_string(), "eol:↲,tab:<->,multispace:---+");
+    }
+
+    #[test]
+    fn list_chars_should_keep_unknown_items() {
+        let lcs: ListChars = "trail:-,future:x".parse().unwrap();
+        assert_eq!(lcs.other, vec![("future".to_string(), "x".to_string())]);
+        assert_eq!(lcs.to_string(), "trail:-,future:x");
+    }
+
+    #[test]
+    fn list_chars_should_fail_if_invalid() {
+        assert!("trail".parse::<ListChars>().is_err());
+        assert!("tab:>".parse::<ListChars>().is_err());
+        assert!("trail:".parse::<ListChars>().is_err());
+        assert!("trail:ab".parse::<ListChars>().is_err());
+    }
+
+    #[test]
+    fn fill_chars_should_round_trip() {
+        let fcs = FillChars {
+            vert: Some('|'),
+            fold: Some('-'),
+            other: vec![("horiz".to_string(), "━".to_string())],
+            ..Default::default()
+        };
+
+        let s = fcs.to_string();
+        assert_eq!(s, "vert:|,fold:-,horiz:━");
+        assert_eq!(s.parse::<FillChars>().unwrap(), fcs);
+    }
+}
@@ -0,0 +1,101 @@
+use std::{fmt, io, str::FromStr};
+
+/// Comma-separated list of items used by options such as `'wildignore'`,
+/// `'path'` and `'runtimepath'`, where a backslash escapes a comma within an
+/// item
+///
+/// ### Notes
+///
+/// * Empty items are kept, as they are meaningful to some options (an empty
+///   item within `'path'` refers to the current directory)
+///
+/// ### Examples
+///
+/// ```
+/// use vimvar::options::CommaList;
+///
+/// let list: CommaList = r"*.o,*.pyc,dir\,with\,commas".parse().unwrap();
+/// assert_eq!(list.items(), ["*.o", "*.pyc", "dir,with,commas"]);
+/// assert_eq!(list.to_string(), r"*.o,*.pyc,dir\,with\,commas");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CommaList(Vec<String>);
+
+impl CommaList {
+    /// Creates a new list from `items`
+    pub fn new(items: Vec<String>) -> Self {
+        Self(items)
+    }
+
+    /// Returns the items within the list
+    pub fn items(&self) -> &[String] {
+        &self.0
+    }
+
+    /// Returns true if the list contains `item`
+    pub fn contains(&self, item: &str) -> bool {
+        self.0.iter().any(|x| x == item)
+    }
+
+    /// Consumes the list, returning its items
+    pub fn into_items(self) -> Vec<String> {
+        self.0
+    }
+}
+
+impl FromStr for CommaList {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Ok(Self::default());
+        }
+
+        let mut items = Vec::new();
+        let mut item = String::new();
+        let mut chars = s.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' if chars.peek() == Some(&',') => item.push(chars.next().unwrap()),
+                ',' => items.push(std::mem::take(&mut item)),
+                c => item.push(c),
+            }
+        }
+        items.push(item);
+
+        Ok(Self(items))
+    }
+}
+
+impl fmt::Display for CommaList {
+    /// Writes the list as it would appear within the option's value
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, item) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}", item.replace(',', "\\,"))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_keep_empty_items() {
+        let list: CommaList = ".,/usr/include,,".parse().unwrap();
+        assert_eq!(list.items(), [".", "/usr/include", "", ""]);
+        assert_eq!(list.to_string(), ".,/usr/include,,");
+    }
+
+    #[test]
+    fn should_parse_empty_string_as_empty_list() {
+        let list: CommaList = "".parse().unwrap();
+        assert!(list.items().is_empty());
+        assert_eq!(list.to_string(), "");
+    }
+}
@@ -0,0 +1,67 @@
+use std::{fmt, io, str::FromStr};
+
+/// String of single-character flags used by options such as
+/// `'formatoptions'`, `'shortmess'` and `'cpoptions'`, where each character
+/// enables a behavior
+///
+/// ### Examples
+///
+/// ```
+/// use vimvar::options::Flags;
+///
+/// let mut flags: Flags = "tcqj".parse().unwrap();
+/// assert!(flags.contains('j'));
+///
+/// flags.remove('t');
+/// flags.insert('r');
+/// assert_eq!(flags.to_string(), "cqjr");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Flags(Vec<char>);
+
+impl Flags {
+    /// Returns true if `flag` is enabled
+    pub fn contains(&self, flag: char) -> bool {
+        self.0.contains(&flag)
+    }
+
+    /// Enables `flag`, returning false if it was already enabled
+    pub fn insert(&mut self, flag: char) -> bool {
+        if self.contains(flag) {
+            false
+        } else {
+            self.0.push(flag);
+            true
+        }
+    }
+
+    /// Disables `flag`, returning false if it was not enabled
+    pub fn remove(&mut self, flag: char) -> bool {
+        let len = self.0.len();
+        self.0.retain(|x| *x != flag);
+        self.0.len() != len
+    }
+
+    /// Returns the enabled flags in the order they appear within the option
+    pub fn iter(&self) -> impl Iterator<Item = char> + '_ {
+        self.0.iter().copied()
+    }
+}
+
+impl FromStr for Flags {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(s.chars().collect()))
+    }
+}
+
+impl fmt::Display for Flags {
+    /// Writes the flags as they would appear within the option's value
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for flag in &self.0 {
+            write!(f, "{}", flag)?;
+        }
+        Ok(())
+    }
+}
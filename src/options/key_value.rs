@@ -0,0 +1,107 @@
+use std::{fmt, io, str::FromStr};
+
+/// Comma-separated list of items that are either a `key:value` pair or a
+/// lone key, used by options such as `'diffopt'` and `'guicursor'`
+///
+/// ### Examples
+///
+/// ```
+/// use vimvar::options::KeyValueList;
+///
+/// let diffopt: KeyValueList = "internal,filler,algorithm:histogram".parse().unwrap();
+/// assert!(diffopt.contains_key("filler"));
+/// assert_eq!(diffopt.get("algorithm"), Some("histogram"));
+/// assert_eq!(diffopt.to_string(), "internal,filler,algorithm:histogram");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct KeyValueList(Vec<(String, Option<String>)>);
+
+impl KeyValueList {
+    /// Returns the items within the list in order, where a lone key has no
+    /// value
+    pub fn items(&self) -> &[(String, Option<String>)] {
+        &self.0
+    }
+
+    /// Returns true if the list contains `key`, with or without a value
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.0.iter().any(|(k, _)| k == key)
+    }
+
+    /// Returns the value of the first item with `key`
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(k, _)| k == key)
+            .and_then(|(_, v)| v.as_deref())
+    }
+
+    /// Sets the value of the first item with `key`, adding a new item to the
+    /// end of the list if there is none
+    pub fn set(&mut self, key: &str, value: Option<&str>) {
+        let value = value.map(ToString::to_string);
+        match self.0.iter_mut().find(|(k, _)| k == key) {
+            Some((_, v)) => *v = value,
+            None => self.0.push((key.to_string(), value)),
+        }
+    }
+
+    /// Removes every item with `key`, returning false if there were none
+    pub fn remove(&mut self, key: &str) -> bool {
+        let len = self.0.len();
+        self.0.retain(|(k, _)| k != key);
+        self.0.len() != len
+    }
+}
+
+impl FromStr for KeyValueList {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Ok(Self::default());
+        }
+
+        Ok(Self(
+            s.split(',')
+                .map(|item| match item.split_once(':') {
+                    Some((key, value)) => (key.to_string(), Some(value.to_string())),
+                    None => (item.to_string(), None),
+                })
+                .collect(),
+        ))
+    }
+}
+
+impl fmt::Display for KeyValueList {
+    /// Writes the list as it would appear within the option's value
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (key, value)) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            match value {
+                Some(value) => write!(f, "{}:{}", key, value)?,
+                None => write!(f, "{}", key)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_support_setting_and_removing_items() {
+        let mut list: KeyValueList = "internal,context:6".parse().unwrap();
+
+        list.set("context", Some("3"));
+        list.set("iwhite", None);
+        assert!(list.remove("internal"));
+        assert!(!list.remove("internal"));
+
+        assert_eq!(list.to_string(), "context:3,iwhite");
+    }
+}
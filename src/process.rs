@@ -0,0 +1,810 @@
+use crate::{de, Cmd, Editor, Evaluated};
+use serde::{de::DeserializeOwned, Deserialize};
+use std::{
+    fs::File,
+    io::{self, BufReader, Read, Write},
+    process::Command,
+};
+
+/// Name of the environment variable used to pass the path of the file that
+/// the editor writes the result into
+const OUTPUT_ENV_VAR: &str = "VIMVAR_OUTPUT";
+
+/// Name of the environment variable used to pass the expression to evaluate
+/// to the editor, which avoids needing to escape it within the command line
+const EXPR_ENV_VAR: &str = "VIMVAR_EXPR";
+
+/// Script sourced after the config is loaded to evaluate the expression found
+/// in [`EXPR_ENV_VAR`], writing out the result wrapped in a JSON object where
+/// `ok` is the value and `err` is the exception thrown while evaluating
+///
+/// ### Notes
+///
+/// * The result is written with `writefile()` to the file found in
+///   [`OUTPUT_ENV_VAR`] rather than printed, so that anything else printed
+///   (by plugins during startup, for instance) cannot interfere with it and
+///   large values avoid being passed through a buffer
+/// * Script-local helper functions are defined ahead of evaluation and can be
+///   referenced by expressions built within this crate:
+///     * `s:bytes(value)` converts a String or Blob into a List of its bytes,
+///       which are otherwise reinterpreted by `json_encode()` when they are
+///       not valid UTF-8
+///     * `s:tagged(value)` converts a value into a Dict of `t` (the result of
+///       `type()`) and `v` (the value in a form `json_encode()` supports),
+///       recursively tagging the items of Lists and Dicts
+///     * `s:eval_each(exprs)` evaluates each expression within a List,
+///       producing a List of the same `ok`/`err` Dicts as the script itself so
+///       that one failing expression does not prevent loading the others
+///     * `s:walk(value, path)` follows a List of `path` keys (Strings) and
+///       indexes (Numbers) into `value`, resulting in 0 if any key is missing
+///       or used with a value that cannot contain it, such as a String key
+///       with a List, where keys only apply to Dicts and indexes only to
+///       Lists and Blobs
+///     * `s:strip_prefix(dict, prefix)` copies a Dict whose keys all start
+///       with `prefix`, removing it from each key
+///     * `s:option(name, scope)` converts the option `name` read using
+///       `scope` (such as `&l:`) into a Dict of its `type` (boolean, number or
+///       string) and `value`, where booleans are told apart from numbers by
+///       the lack of `=` in the output of `:set name?`
+///     * `s:options()` lists every option as a Dict of its `name`, `type` and
+///       effective `value`, alongside the metadata from
+///       `nvim_get_all_options_info()` when using neovim
+///     * `s:open(path, lines)` edits `path` without a swap file, triggering
+///       filetype detection, ftplugins, modelines and any other autocommands,
+///       where a List of `lines` is used in place of the file's contents as
+///       if they had been read from it
+///     * `s:file_settings(path)` opens `path` and returns its `filetype`,
+///       the effective value of every buffer-local and window-local option
+///       as `options` and the buffer's `vars` (see `s:tagged()`)
+///     * `s:highlights()` describes `'termguicolors'`, `'background'`, the
+///       colorscheme, the terminal's 16 colors and every highlight group,
+///       where each group has its immediate `link` alongside the `gui` and
+///       `cterm` colors and attributes of the group it resolves to
+///     * `s:scratch(filetype, path)` loads the lines of `path` into a new
+///       buffer without a swap file and sets its `filetype`, triggering
+///       ftplugins, indent scripts and syntax highlighting
+///     * `s:highlight_text(filetype, path, spans_lua)` creates a scratch
+///       buffer and returns the highlight group of each span of text as
+///       `[line, start, end, group]` alongside the `palette` from
+///       `s:highlights()`, using `synID()` for vim and running `spans_lua`
+///       (see [`SPANS_LUA`]) for neovim
+///     * `s:format(filetype, path, cmd)` creates a scratch buffer, runs the
+///       Ex command `cmd` within it and returns the resulting lines
+///     * `s:rgb(color)` converts a color name (such as `LightBlue`) into
+///       `#rrggbb` using `v:colornames` where possible
+///     * `s:verbose(cmd)` runs `cmd` with `:verbose` and returns the lines it
+///       prints, switching messages to English first so that `Last set from`
+///       can be found regardless of the user's language
+/// * When `json_encode()` rejects the result (such as when it contains a
+///   Funcref) or produces invalid JSON (such as `NaN` for a float), the
+///   result is encoded again after replacing each value that cannot be
+///   encoded with a placeholder Dict (see [`crate::Unencodable`])
+const EVAL_SCRIPT: &str = r#"function! s:bytes(value) abort
+  if type(a:value) == type(0z)
+    return map(range(len(a:value)), {_, i -> a:value[i]})
+  elseif type(a:value) == type('')
+    return map(range(strlen(a:value)), {_, i -> char2nr(a:value[i])})
+  endif
+  throw 'vimvar: expected String or Blob, but got ' . string(a:value)
+endfunction
+
+function! s:tagged(value) abort
+  let l:type = type(a:value)
+  if l:type == 0 || l:type == 1
+    return {'t': l:type, 'v': a:value}
+  elseif l:type == 2
+    return {'t': l:type, 'v': get(a:value, 'name')}
+  elseif l:type == 3 || l:type == 4
+    return {'t': l:type, 'v': map(copy(a:value), {_, item -> s:tagged(item)})}
+  elseif l:type == 6
+    return {'t': l:type, 'v': a:value ? v:true : v:false}
+  elseif l:type == 10
+    return {'t': l:type, 'v': s:bytes(a:value)}
+  endif
+
+  " Floats (to support inf and nan), special values, jobs, channels and
+  " anything else are represented by their string form
+  return {'t': l:type, 'v': string(a:value)}
+endfunction
+
+function! s:eval_each(exprs) abort
+  let l:results = []
+  for l:expr in a:exprs
+    try
+      call add(l:results, {'ok': eval(l:expr)})
+    catch
+      call add(l:results, {'err': v:exception})
+    endtry
+  endfor
+  return l:results
+endfunction
+
+function! s:walk(value, path) abort
+  let l:value = a:value
+  for l:key in a:path
+    let l:types = type(l:key) == type('') ? [type({})] : [type([]), type(0z)]
+    if index(l:types, type(l:value)) < 0
+      return 0
+    endif
+    let l:value = get(l:value, l:key)
+  endfor
+  return l:value
+endfunction
+
+function! s:strip_prefix(dict, prefix) abort
+  let l:stripped = {}
+  for l:name in keys(a:dict)
+    let l:stripped[l:name[len(a:prefix):]] = a:dict[l:name]
+  endfor
+  return l:stripped
+endfunction
+
+function! s:option(name, scope) abort
+  if !exists('+' . a:name)
+    throw 'vimvar: unknown option ' . a:name
+  endif
+
+  let l:value = eval(a:scope . a:name)
+  if type(l:value) == type('')
+    return {'type': 'string', 'value': l:value}
+  endif
+
+  let l:cmd = get({'&l:': 'setlocal', '&g:': 'setglobal'}, a:scope, 'set')
+  let l:type = execute(l:cmd . ' ' . a:name . '?') =~# '=' ? 'number' : 'boolean'
+  return {'type': l:type, 'value': l:value}
+endfunction
+
+function! s:options() abort
+  if exists('*nvim_get_all_options_info')
+    let l:options = values(nvim_get_all_options_info())
+    return map(l:options, {_, info -> extend(info, {'value': eval('&' . info.name)})})
+  endif
+
+  " getcompletion() also includes entries such as 'all' and 'termcap' that
+  " are not options
+  let l:names = filter(getcompletion('', 'option'), {_, name -> exists('+' . name)})
+  return map(l:names, {_, name -> extend(s:option(name, '&'), {'name': name})})
+endfunction
+
+function! s:open(path, lines) abort
+  " The config may have modified the initial buffer, which is discarded
+  if a:lines is v:null
+    execute 'noswapfile edit! ' . fnameescape(a:path)
+  else
+    noswapfile enew!
+    execute 'file ' . fnameescape(a:path)
+    call setline(1, a:lines)
+    execute 'doautocmd BufRead ' . fnameescape(a:path)
+  endif
+endfunction
+
+function! s:file_settings(path) abort
+  call s:open(a:path, v:null)
+
+  let l:options = {}
+  for l:name in keys(getbufvar('', '&')) + keys(getwinvar(0, '&'))
+    let l:options[l:name] = s:option(l:name, '&')
+  endfor
+  return {'filetype': &filetype, 'options': l:options, 'vars': s:tagged(b:)}
+endfunction
+
+function! s:rgb(color) abort
+  if a:color =~# '^#' || !exists('v:colornames')
+    return a:color
+  elseif empty(v:colornames)
+    runtime colors/lists/default.vim
+  endif
+  return get(v:colornames, tolower(a:color), a:color)
+endfunction
+
+function! s:highlights() abort
+  let l:groups = {}
+  if exists('*nvim_get_hl')
+    let l:links = nvim_get_hl(0, {})
+    for [l:name, l:hl] in items(nvim_get_hl(0, {'link': v:false}))
+      let l:gui = {'fg': get(l:hl, 'fg', ''), 'bg': get(l:hl, 'bg', ''), 'sp': get(l:hl, 'sp', ''), 'attrs': l:hl}
+      let l:cterm = {'fg': get(l:hl, 'ctermfg', ''), 'bg': get(l:hl, 'ctermbg', ''), 'sp': '', 'attrs': get(l:hl, 'cterm', {})}
+      let l:link = get(get(l:links, l:name, {}), 'link', '')
+      let l:groups[l:name] = {'link': l:link, 'gui': l:gui, 'cterm': l:cterm}
+    endfor
+  else
+    for l:hl in hlget()
+      let l:resolved = get(hlget(l:hl.name, v:true), 0, {})
+      let l:gui = {'attrs': get(l:resolved, 'gui', {})}
+      let l:cterm = {'attrs': get(l:resolved, 'cterm', {})}
+      for [l:key, l:gui_key, l:cterm_key] in [['fg', 'guifg', 'ctermfg'], ['bg', 'guibg', 'ctermbg'], ['sp', 'guisp', 'ctermul']]
+        let l:gui[l:key] = s:rgb(get(l:resolved, l:gui_key, ''))
+        let l:cterm[l:key] = get(l:resolved, l:cterm_key, '')
+      endfor
+      let l:groups[l:hl.name] = {'link': get(l:hl, 'linksto', ''), 'gui': l:gui, 'cterm': l:cterm}
+    endfor
+  endif
+
+  let l:ansi = get(g:, 'terminal_ansi_colors', [])
+  let l:terminal = map(range(16), {_, i -> s:rgb(get(g:, 'terminal_color_' . i, get(l:ansi, i, '')))})
+  return {'colorscheme': get(g:, 'colors_name', ''), 'background': &background, 'termguicolors': &termguicolors ? v:true : v:false, 'groups': l:groups, 'terminal_colors': l:terminal}
+endfunction
+
+function! s:scratch(filetype, path) abort
+  noswapfile enew!
+  call setline(1, readfile(a:path))
+  let &l:filetype = a:filetype
+endfunction
+
+function! s:highlight_text(filetype, path, spans_lua) abort
+  call s:scratch(a:filetype, a:path)
+
+  let l:spans = []
+  if has('nvim')
+    let l:spans = luaeval(a:spans_lua, bufnr(''))
+  else
+    for l:lnum in range(1, line('$'))
+      let l:line = getline(l:lnum)
+      let [l:start, l:group] = [0, '']
+      for l:col in range(1, len(l:line))
+        let l:current = synIDattr(synID(l:lnum, l:col, 1), 'name')
+        if l:col > 1 && l:current !=# l:group
+          call add(l:spans, [l:lnum - 1, l:start, l:col - 1, l:group])
+          let l:start = l:col - 1
+        endif
+        let l:group = l:current
+      endfor
+      if !empty(l:line)
+        call add(l:spans, [l:lnum - 1, l:start, len(l:line), l:group])
+      endif
+    endfor
+  endif
+
+  " An empty Lua table may be converted into a Dict
+  if type(l:spans) != type([])
+    let l:spans = []
+  endif
+  return {'spans': l:spans, 'palette': s:highlights()}
+endfunction
+
+function! s:format(filetype, path, cmd) abort
+  call s:scratch(a:filetype, a:path)
+  execute a:cmd
+  return getline(1, '$')
+endfunction
+
+function! s:verbose(cmd) abort
+  return split(execute(['silent! language messages C', 'verbose ' . a:cmd]), "\n")
+endfunction
+
+function! s:sanitized(value) abort
+  let l:type = type(a:value)
+  if l:type == 3 || l:type == 4
+    return map(copy(a:value), {_, item -> s:sanitized(item)})
+  elseif l:type == 5 && string(a:value) !~# 'inf\|nan'
+    return a:value
+  elseif l:type == 2 || l:type == 5 || l:type == 8 || l:type == 9 || l:type > 10
+    let l:kind = get({2: 'funcref', 5: 'float', 8: 'job', 9: 'channel'}, l:type, 'other')
+    return {'__vimvar_unencodable__': l:kind, 'repr': string(a:value)}
+  endif
+  return a:value
+endfunction
+
+try
+  let s:value = eval($VIMVAR_EXPR)
+  try
+    let s:result = json_encode({'ok': s:value})
+  catch
+    let s:result = ''
+  endtry
+
+  " Some versions encode non-finite floats as Infinity and NaN instead of failing
+  if empty(s:result) || s:result =~# '\<\(Infinity\|NaN\)\>'
+    let s:result = json_encode({'ok': s:sanitized(s:value)})
+  endif
+catch
+  let s:result = json_encode({'err': v:exception})
+endtry
+call writefile([s:result], $VIMVAR_OUTPUT)
+qall!
+"#;
+
+/// Lua chunk used by `s:highlight_text()` to find the spans of the buffer
+/// given as `_A` using neovim, where the group at each position is the one
+/// drawn with the highest priority among treesitter (when started for the
+/// buffer), semantic tokens, extmarks and syntax items
+///
+/// ### Notes
+///
+/// * Passed from here rather than defined within [`EVAL_SCRIPT`] so that the
+///   script remains loadable by versions of vim without heredocs (`=<<`)
+pub(crate) const SPANS_LUA: &str = r#"(function(buf)
+  local active = vim.treesitter.highlighter.active[buf] ~= nil
+  if active then
+    pcall(function() vim.treesitter.get_parser(buf):parse(true) end)
+  end
+
+  local filter = { syntax = true, treesitter = active, extmarks = true, semantic_tokens = true }
+  -- Ordered by default priority, with later kinds winning ties
+  local defaults = { { 'syntax', 0 }, { 'treesitter', 100 }, { 'semantic_tokens', 125 }, { 'extmarks', 4096 } }
+  local function group_at(row, col)
+    local pos = vim.inspect_pos(buf, row, col, filter)
+    local group, priority = '', -1
+    for _, kind in ipairs(defaults) do
+      local default = kind[2]
+      for _, item in ipairs(pos[kind[1]] or {}) do
+        local opts = item.opts or {}
+        local name = item.hl_group or opts.hl_group
+        local p = tonumber((item.metadata or {}).priority) or opts.priority or default
+        if name and name ~= '' and p >= priority then
+          group, priority = name, p
+        end
+      end
+    end
+    return group
+  end
+
+  local spans = {}
+  for row, line in ipairs(vim.api.nvim_buf_get_lines(buf, 0, -1, false)) do
+    local start, group, col = 0, nil, 0
+    while col < #line do
+      local current = group_at(row - 1, col)
+      if group ~= nil and current ~= group then
+        table.insert(spans, { row - 1, start, col, group })
+        start = col
+      end
+      group = current
+
+      -- Skip the continuation bytes of multibyte characters
+      col = col + 1
+      while col < #line and line:byte(col + 1) >= 0x80 and line:byte(col + 1) < 0xC0 do
+        col = col + 1
+      end
+    end
+    if #line > 0 then
+      table.insert(spans, { row - 1, start, #line, group })
+    end
+  end
+  return spans
+end)(_A)
+"#;
+
+/// Name of the environment variable used to pass the Lua chunk to evaluate to
+/// the editor
+const LUA_ENV_VAR: &str = "VIMVAR_LUA";
+
+/// Lua equivalent of [`EVAL_SCRIPT`] that runs the chunk found in
+/// [`LUA_ENV_VAR`], encoding its return value with `vim.json.encode`
+///
+/// ### Notes
+///
+/// * The chunk is first compiled as an expression (`return <chunk>`) and then
+///   as a series of statements, mirroring the behavior of `:lua =`
+/// * `nil` and `vim.NIL` are both encoded as `null`
+/// * Functions, threads and userdata (other than `vim.NIL`) cannot be encoded,
+///   so the result is checked for them ahead of time in order to report
+///   where they are located
+/// * The result is written to the file found in [`OUTPUT_ENV_VAR`], matching
+///   the Vimscript variant
+const EVAL_LUA_SCRIPT: &str = r#"local function check(value, path)
+  local kind = type(value)
+  if kind == 'function' or kind == 'thread' or (kind == 'userdata' and value ~= vim.NIL) then
+    error(string.format('cannot encode %s found at %s', kind, path), 0)
+  elseif kind == 'table' then
+    for key, item in pairs(value) do
+      check(item, string.format('%s[%s]', path, vim.inspect(key)))
+    end
+  end
+end
+
+local ok, result = pcall(function()
+  local source = os.getenv('VIMVAR_LUA')
+  local chunk = loadstring('return ' .. source, '=vimvar')
+  if not chunk then
+    chunk = assert(loadstring(source, '=vimvar'))
+  end
+
+  local value = chunk()
+  check(value, 'result')
+  if value == nil then
+    value = vim.NIL
+  end
+  return vim.json.encode({ ok = value })
+end)
+
+if not ok then
+  result = vim.json.encode({ err = tostring(result) })
+end
+
+vim.fn.writefile({ result }, os.getenv('VIMVAR_OUTPUT'))
+vim.cmd('qall!')
+"#;
+
+/// Represents the result written by [`EVAL_SCRIPT`] and [`EVAL_LUA_SCRIPT`]
+/// (as well as each result of `s:eval_each()`), being either the value or the
+/// exception thrown while producing it
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Outcome<T> {
+    Ok(T),
+    Err(String),
+}
+
+/// Spawns neovim/vim using the editor's config and evaluates the Vimscript
+/// `expr` within it, returning the result of the expression after being
+/// encoded as JSON by the editor and decoded on our side
+///
+/// ### Notes
+///
+/// * `expr` is passed to the editor through an environment variable and
+///   evaluated with `eval()`, so it never needs to be escaped
+/// * An exception thrown while evaluating `expr` is reported as an error
+/// * The result is deserialized into `T` as it is read, where values that
+///   fail to convert are reported by their path relative to `root`
+pub(crate) fn eval_json<T>(
+    editor: &Editor,
+    expr: &str,
+    root: de::Root<'_>,
+) -> io::Result<Evaluated<T>>
+where
+    T: DeserializeOwned,
+{
+    run(
+        editor,
+        EVAL_SCRIPT,
+        ".vim",
+        (EXPR_ENV_VAR, expr),
+        root,
+        false,
+    )
+}
+
+/// Same as [`eval_json`], but starts vim with `t_Co` set as a 256 color
+/// terminal would, as it is otherwise empty without a terminal and leads
+/// colorschemes to skip defining their cterm colors
+pub(crate) fn eval_json_with_colors<T>(
+    editor: &Editor,
+    expr: &str,
+    root: de::Root<'_>,
+) -> io::Result<Evaluated<T>>
+where
+    T: DeserializeOwned,
+{
+    run(
+        editor,
+        EVAL_SCRIPT,
+        ".vim",
+        (EXPR_ENV_VAR, expr),
+        root,
+        true,
+    )
+}
+
+/// Spawns neovim using the editor's config and evaluates the Lua `chunk`
+/// within it, returning the chunk's return value after being encoded as JSON
+/// by the editor and decoded on our side
+///
+/// ### Notes
+///
+/// * Only supported by neovim, returning an [`io::Error`] for vim
+/// * `chunk` is passed to the editor through an environment variable, so it
+///   never needs to be escaped
+/// * An error raised while running `chunk` is reported as an error
+pub(crate) fn eval_lua_json<T>(editor: &Editor, chunk: &str) -> io::Result<Evaluated<T>>
+where
+    T: DeserializeOwned,
+{
+    if editor.cmd() != Cmd::Neovim {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("Lua evaluation is only supported by {}", Cmd::Neovim),
+        ));
+    }
+
+    run(
+        editor,
+        EVAL_LUA_SCRIPT,
+        ".lua",
+        (LUA_ENV_VAR, chunk),
+        de::Root::new(""),
+        false,
+    )
+}
+
+/// Spawns neovim/vim using the editor's config, sourcing `script` once the
+/// config is loaded with `input` available as an environment variable, and
+/// decodes the result written by the script into `T`
+///
+/// If `colors` is true, vim is started as if within a 256 color terminal
+fn run<T>(
+    editor: &Editor,
+    script: &str,
+    suffix: &str,
+    input: (&str, &str),
+    root: de::Root<'_>,
+    colors: bool,
+) -> io::Result<Evaluated<T>>
+where
+    T: DeserializeOwned,
+{
+    let cmd = editor.cmd();
+    let config = editor.config();
+    if config.as_os_str().is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "path to vimrc is required for neovim/vim",
+        ));
+    }
+
+    // NOTE: The suffix matters as neovim determines whether to source the
+    //       script as Lua or Vimscript from its extension
+    let (input_var, input) = input;
+    let mut script_file = tempfile::Builder::new()
+        .prefix("vimvar")
+        .suffix(suffix)
+        .tempfile()?;
+    script_file.write_all(script.as_bytes())?;
+    script_file.flush()?;
+
+    let output_file = tempfile::Builder::new()
+        .prefix("vimvar")
+        .suffix(".json")
+        .tempfile()?;
+
+    // NOTE: We have a lot of settings being applied, so documenting
+    //       them here
+    //
+    //       1. -Es is our silent, batch, ex mode
+    //       2. -i NONE removes shada/viminfo file reading and writing
+    //       3. -u {} loads our vimrc, which is required as -Es does
+    //          not load vim scripts by default
+    //       4. -S {} sources our script once the vimrc has been loaded
+    //       5. (vim only, if requested) --cmd sets t_Co as a 256 color
+    //          terminal would, as it is otherwise empty without a terminal
+    //          and leads colorschemes to skip defining their cterm colors
+    //
+    //       The editor is spawned directly rather than through a shell so
+    //       that none of the arguments need shell escaping
+    let mut command = Command::new(cmd.as_str());
+    command.arg("-Es").arg("-i").arg("NONE");
+    if colors && cmd == Cmd::Vim {
+        command.arg("--cmd").arg("set t_Co=256");
+    }
+    let output = command
+        .arg("-u")
+        .arg(config)
+        .arg("-S")
+        .arg(script_file.path())
+        .env(input_var, input)
+        .env(OUTPUT_ENV_VAR, output_file.path())
+        .output()?;
+
+    // If our program failed, we want to report the failure
+    //
+    // NOTE: neovim/vim seems to return exit code 1; so, for now we'll
+    //       ignore that specific exit code for now
+    if !output.status.success() && (output.status.code() != Some(1)) {
+        let code = output
+            .status
+            .code()
+            .as_ref()
+            .map(ToString::to_string)
+            .unwrap_or_else(|| String::from("--"));
+        return Err(io::Error::other(format!(
+            "[Exit code {}]: {}",
+            code,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    let diagnostics = collect_diagnostics(&[&output.stdout, &output.stderr]);
+    let result = read_result(output_file.reopen()?, editor.max_output_size(), root).map_err(
+        |x| match x {
+            de::JsonError::Read(x) => io::Error::new(
+                x.kind(),
+                format!(
+                    "Result from {} {}{}",
+                    cmd,
+                    x,
+                    format_diagnostics(&diagnostics)
+                ),
+            ),
+            de::JsonError::Data(x) => x,
+        },
+    )?;
+
+    Ok(Evaluated {
+        value: unwrap_result(result, input)?,
+        diagnostics,
+    })
+}
+
+/// Reads the JSON result written by the editor into `file`, deserializing the
+/// value within it as it is read, failing if the file is empty or larger than
+/// `max_size` bytes
+fn read_result<T>(
+    file: File,
+    max_size: Option<u64>,
+    root: de::Root<'_>,
+) -> Result<Outcome<T>, de::JsonError>
+where
+    T: DeserializeOwned,
+{
+    let len = file.metadata().map_err(de::JsonError::Read)?.len();
+
+    // Report a better error than the serde one if the output was empty
+    if len == 0 {
+        return Err(de::JsonError::Read(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "was empty",
+        )));
+    }
+
+    if let Some(max_size) = max_size {
+        if len > max_size {
+            return Err(de::JsonError::Read(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "was {} bytes, which exceeds the maximum of {} bytes",
+                    len, max_size
+                ),
+            )));
+        }
+    }
+
+    // NOTE: Limit reading to the size we checked in case the file is still
+    //       being written to by a lingering process
+    let reader = BufReader::new(file.take(len));
+
+    // NOTE: The value is nested within the outcome's `ok` key, which is not
+    //       part of its path
+    de::from_json(serde_json::de::IoRead::new(reader), root.within(1))
+}
+
+/// Collects the non-blank lines printed by the editor to be returned as
+/// diagnostics
+fn collect_diagnostics(outputs: &[&[u8]]) -> Vec<String> {
+    outputs
+        .iter()
+        .flat_map(|output| {
+            String::from_utf8_lossy(output)
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(String::from)
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Formats diagnostics to be appended to an error message
+fn format_diagnostics(diagnostics: &[String]) -> String {
+    if diagnostics.is_empty() {
+        String::new()
+    } else {
+        format!(" (output: {})", diagnostics.join("\n"))
+    }
+}
+
+/// Extracts the value from the outcome produced by [`EVAL_SCRIPT`] or
+/// [`EVAL_LUA_SCRIPT`], converting a reported exception into an [`io::Error`]
+fn unwrap_result<T>(result: Outcome<T>, expr: &str) -> io::Result<T> {
+    match result {
+        Outcome::Ok(value) => Ok(value),
+        Outcome::Err(err) => Err(io::Error::other(format!(
+            "Failed to evaluate \"{}\": {}",
+            expr, err
+        ))),
+    }
+}
+
+/// Converts `s` into a single-quoted Vimscript string literal, which treats
+/// every character literally except for the quote itself
+pub(crate) fn vim_string_literal(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collect_diagnostics_should_include_non_blank_lines_from_all_outputs() {
+        let diagnostics = collect_diagnostics(&[b"  1 \nplugin loaded\n\n", b"E492: oops\n"]);
+
+        assert_eq!(diagnostics, vec!["1", "plugin loaded", "E492: oops"]);
+    }
+
+    /// Reads `contents` as the result, returning the value or the message of
+    /// the error alongside whether it is a data error
+    fn read_result_from(
+        contents: &[u8],
+        max_size: Option<u64>,
+    ) -> Result<Outcome<Vec<u8>>, (bool, String)> {
+        let mut file = tempfile::tempfile().unwrap();
+        file.write_all(contents).unwrap();
+        file.flush().unwrap();
+
+        read_result(reopen_at_start(file), max_size, de::Root::new("g:list")).map_err(|x| match x {
+            de::JsonError::Read(x) => (false, x.to_string()),
+            de::JsonError::Data(x) => (true, x.to_string()),
+        })
+    }
+
+    #[test]
+    fn read_result_should_parse_json_from_file() {
+        match read_result_from(br#"{"ok":[1,2,3]}"#, Some(1024)) {
+            Ok(Outcome::Ok(value)) => assert_eq!(value, vec![1, 2, 3]),
+            _ => panic!("Unexpected result"),
+        }
+        match read_result_from(br#"{"err":"E121: oops"}"#, Some(1024)) {
+            Ok(Outcome::Err(msg)) => assert_eq!(msg, "E121: oops"),
+            _ => panic!("Unexpected result"),
+        }
+    }
+
+    #[test]
+    fn read_result_should_report_path_of_value_that_fails_to_convert() {
+        let (data, msg) = read_result_from(br#"{"ok":[1,-2,3]}"#, None)
+            .err()
+            .expect("Unexpected success");
+        assert!(data);
+        assert!(
+            msg.starts_with("g:list[1]: "),
+            "Unexpected message: {}",
+            msg
+        );
+    }
+
+    #[test]
+    fn read_result_should_fail_if_file_is_empty() {
+        let err = read_result_from(b"", None)
+            .err()
+            .expect("Unexpected success");
+        assert_eq!(err, (false, String::from("was empty")));
+    }
+
+    #[test]
+    fn read_result_should_fail_if_file_exceeds_max_size() {
+        let err = read_result_from(br#"{"ok":[1,2,3]}"#, Some(4))
+            .err()
+            .expect("Unexpected success");
+        assert_eq!(
+            err,
+            (
+                false,
+                String::from("was 14 bytes, which exceeds the maximum of 4 bytes")
+            )
+        );
+    }
+
+    fn reopen_at_start(mut file: File) -> File {
+        use std::io::{Seek, SeekFrom};
+        file.seek(SeekFrom::Start(0)).unwrap();
+        file
+    }
+
+    #[test]
+    fn unwrap_result_should_return_ok_value() {
+        let value = unwrap_result(Outcome::Ok(vec![1, 2]), "[1, 2]").unwrap();
+        assert_eq!(value, vec![1, 2]);
+    }
+
+    #[test]
+    fn unwrap_result_should_return_err_as_io_error() {
+        let err = unwrap_result::<()>(
+            Outcome::Err(String::from("E121: Undefined variable: g:nope")),
+            "g:nope",
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Failed to evaluate \"g:nope\": E121: Undefined variable: g:nope"
+        );
+    }
+
+    #[test]
+    fn vim_string_literal_should_wrap_in_single_quotes() {
+        assert_eq!(vim_string_literal("colors/*.vim"), "'colors/*.vim'");
+    }
+
+    #[test]
+    fn vim_string_literal_should_escape_single_quotes() {
+        assert_eq!(vim_string_literal("it's \"here\""), "'it''s \"here\"'");
+    }
+}
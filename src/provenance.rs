@@ -0,0 +1,247 @@
+use std::path::{Path, PathBuf};
+
+/// Prefix of the message printed by `:verbose` describing where something was
+/// last defined or changed
+const LAST_SET_PREFIX: &str = "Last set from ";
+
+/// Represents a value alongside where the editor recorded it as last being
+/// set, as reported by `:verbose` (such as `Last set from ~/.vimrc line 3`)
+///
+/// ### Examples
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// use vimvar::Editor;
+///
+/// let provenance = Editor::detect()?.option_provenance("shiftwidth")?;
+/// match (&provenance.file, provenance.line) {
+///     (Some(file), Some(line)) => {
+///         println!("{} (set in {:?} line {})", provenance.value, file, line)
+///     }
+///     _ => println!("{} (never set by a script)", provenance.value),
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Provenance<T> {
+    /// The value that was set
+    pub value: T,
+
+    /// Script that last set the value, or `None` if it has not been set or
+    /// was set from somewhere other than a script (such as a modeline)
+    pub file: Option<PathBuf>,
+
+    /// Line within the script (or other source) that last set the value
+    pub line: Option<u32>,
+
+    /// Description of where the value was last set exactly as printed after
+    /// `Last set from`, such as `~/.vimrc line 3` or `-c argument`
+    pub set_from: Option<String>,
+}
+
+impl<T> Provenance<T> {
+    /// Creates a provenance for `value` from the `Last set from` message found
+    /// within `lines`, if any
+    pub(crate) fn new(value: T, last_set: Option<&str>) -> Self {
+        let set_from = last_set.and_then(|line| line.trim().strip_prefix(LAST_SET_PREFIX));
+        let (source, line) = match set_from.and_then(split_line) {
+            Some((source, line)) => (Some(source), Some(line)),
+            None => (set_from, None),
+        };
+
+        // Sources that are not scripts are described by name (such as
+        // "modeline" or "Lua"), whereas scripts are always absolute paths,
+        // possibly abbreviated using ~
+        let file = source
+            .filter(|x| x.starts_with('~') || Path::new(x).is_absolute())
+            .map(|x| PathBuf::from(shellexpand::tilde(x).as_ref()));
+
+        Self {
+            value,
+            file,
+            line,
+            set_from: set_from.map(String::from),
+        }
+    }
+}
+
+/// Splits `Last set from` text such as `~/.vimrc line 3` into the source and
+/// its line number
+fn split_line(set_from: &str) -> Option<(&str, u32)> {
+    let (source, line) = set_from.rsplit_once(" line ")?;
+    Some((source, line.parse().ok()?))
+}
+
+/// Returns true if `line` is a `Last set from` message
+pub(crate) fn is_last_set(line: &str) -> bool {
+    line.trim_start().starts_with(LAST_SET_PREFIX)
+}
+
+/// Finds the first `Last set from` message within `lines`
+pub(crate) fn find_last_set(lines: &[String]) -> Option<&str> {
+    lines
+        .iter()
+        .map(String::as_str)
+        .find(|line| is_last_set(line))
+}
+
+/// Returns the `Last set from` message immediately following the entry at
+/// `index` within a listing, if any
+pub(crate) fn last_set_after(lines: &[String], index: usize) -> Option<&str> {
+    lines
+        .get(index + 1)
+        .map(String::as_str)
+        .filter(|line| is_last_set(line))
+}
+
+/// Finds the row for the user command `name` within the table printed by
+/// `:command`, returning the index of the row and its definition
+///
+/// ### Notes
+///
+/// * The definition is read from the column under the `Definition` header,
+///   where names too long for their column push the definition further right
+pub(crate) fn find_command(lines: &[String], name: &str) -> Option<(usize, String)> {
+    let column = lines.iter().find_map(|line| {
+        if line.trim_start().starts_with("Name") {
+            line.find("Definition")
+        } else {
+            None
+        }
+    })?;
+
+    lines.iter().enumerate().find_map(|(i, row)| {
+        // Every row starts with four columns of flags (such as ! or b)
+        if row.get(4..)?.split_whitespace().next() != Some(name) {
+            return None;
+        }
+
+        let mut start = column.min(row.len());
+        while start > 0 && start < row.len() && !row[..start].ends_with(' ') {
+            start += row[start..].chars().next().map_or(1, char::len_utf8);
+        }
+        Some((i, row.get(start..).unwrap_or_default().trim().to_string()))
+    })
+}
+
+/// Finds the row for the mapping of `lhs` within the listing printed by
+/// `:map`, returning the index of the row
+pub(crate) fn find_mapping(lines: &[String], lhs: &str) -> Option<usize> {
+    // Every row starts with three columns describing the modes
+    lines
+        .iter()
+        .position(|row| row.get(3..).and_then(|x| x.split_whitespace().next()) == Some(lhs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(s: &str) -> Vec<String> {
+        s.lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn provenance_should_parse_file_and_line() {
+        let p = Provenance::new(3, Some("\tLast set from /tmp/my vimrc line 12"));
+        assert_eq!(p.value, 3);
+        assert_eq!(p.file, Some(PathBuf::from("/tmp/my vimrc")));
+        assert_eq!(p.line, Some(12));
+        assert_eq!(p.set_from.as_deref(), Some("/tmp/my vimrc line 12"));
+    }
+
+    #[test]
+    fn provenance_should_expand_home_directory() {
+        let p = Provenance::new((), Some("\tLast set from ~/.vimrc line 1"));
+        let home = shellexpand::tilde("~").to_string();
+        assert_eq!(p.file, Some(Path::new(&home).join(".vimrc")));
+        assert_eq!(p.line, Some(1));
+    }
+
+    #[test]
+    fn provenance_should_not_treat_other_sources_as_files() {
+        let p = Provenance::new((), Some("\tLast set from -c argument line 1"));
+        assert_eq!(p.file, None);
+        assert_eq!(p.line, Some(1));
+
+        let p = Provenance::new(
+            (),
+            Some("\tLast set from Lua (run Nvim with -V1 for more details)"),
+        );
+        assert_eq!(p.file, None);
+        assert_eq!(p.line, None);
+        assert_eq!(
+            p.set_from.as_deref(),
+            Some("Lua (run Nvim with -V1 for more details)")
+        );
+    }
+
+    #[test]
+    fn provenance_should_support_never_being_set() {
+        let p = Provenance::new((), None);
+        assert_eq!(p.file, None);
+        assert_eq!(p.line, None);
+        assert_eq!(p.set_from, None);
+    }
+
+    #[test]
+    fn find_last_set_should_skip_other_lines() {
+        let lines = lines("  shiftwidth=3\n\tLast set from /tmp/rc.vim line 1");
+        assert_eq!(
+            find_last_set(&lines),
+            Some("\tLast set from /tmp/rc.vim line 1")
+        );
+        assert_eq!(find_last_set(&lines[..1]), None);
+    }
+
+    #[test]
+    fn find_command_should_match_exact_name() {
+        let lines = lines(concat!(
+            "    Name              Args Address Complete    Definition\n",
+            "    Bar               0                        echo 1\n",
+            "\tLast set from /tmp/rc.vim line 4\n",
+            "!   Barz              *    .       file        echo 'it''s'\n",
+            "\tLast set from /tmp/rc.vim line 5",
+        ));
+
+        assert_eq!(find_command(&lines, "Bar"), Some((1, "echo 1".to_string())));
+        assert_eq!(
+            find_command(&lines, "Barz"),
+            Some((3, "echo 'it''s'".to_string()))
+        );
+        assert_eq!(
+            last_set_after(&lines, 3),
+            Some("\tLast set from /tmp/rc.vim line 5")
+        );
+        assert_eq!(find_command(&lines, "Ba"), None);
+    }
+
+    #[test]
+    fn find_command_should_support_names_longer_than_their_column() {
+        let lines = lines(concat!(
+            "    Name              Args Address Complete    Definition\n",
+            "    AVeryLongCommandNameIndeed 0               echo 1",
+        ));
+        assert_eq!(
+            find_command(&lines, "AVeryLongCommandNameIndeed"),
+            Some((1, "echo 1".to_string()))
+        );
+    }
+
+    #[test]
+    fn find_mapping_should_match_exact_lhs() {
+        let lines = lines(concat!(
+            "n  yy          *@:echo 2<CR>\n",
+            "\tLast set from /tmp/rc.vim line 6\n",
+            "n  y           * :echo 1<CR>\n",
+            "\tLast set from /tmp/rc.vim line 5",
+        ));
+        assert_eq!(find_mapping(&lines, "y"), Some(2));
+        assert_eq!(
+            last_set_after(&lines, 2),
+            Some("\tLast set from /tmp/rc.vim line 5")
+        );
+        assert_eq!(find_mapping(&lines, "x"), None);
+    }
+}
@@ -0,0 +1,88 @@
+use serde::Deserialize;
+use std::{borrow::Cow, ffi::OsString};
+
+/// Represents the exact bytes of a String or Blob loaded from neovim/vim
+/// alongside the `'encoding'` the editor was using at the time
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct RawString {
+    bytes: Vec<u8>,
+    encoding: String,
+}
+
+impl RawString {
+    /// Creates a new raw string from `bytes` that are in the given `encoding`
+    pub fn new(bytes: impl Into<Vec<u8>>, encoding: impl Into<String>) -> Self {
+        Self {
+            bytes: bytes.into(),
+            encoding: encoding.into(),
+        }
+    }
+
+    /// Returns the exact bytes of the string
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Consumes the raw string, returning its exact bytes
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// Returns the value of `'encoding'` tied to the string (such as `utf-8`
+    /// or `latin1`)
+    pub fn encoding(&self) -> &str {
+        &self.encoding
+    }
+
+    /// Returns true if the string was loaded from an editor using a latin1
+    /// `'encoding'`
+    pub fn is_latin1(&self) -> bool {
+        matches!(
+            self.encoding.to_ascii_lowercase().as_str(),
+            "latin1" | "iso-8859-1" | "iso-8859" | "iso8859-1" | "iso8859"
+        )
+    }
+
+    /// Decodes the bytes based on the string's `'encoding'`
+    ///
+    /// ### Notes
+    ///
+    /// * latin1 maps each byte directly to the equivalent character and
+    ///   never fails
+    /// * All other encodings are treated as UTF-8, replacing invalid
+    ///   sequences with U+FFFD
+    ///
+    /// ### Examples
+    ///
+    /// ```
+    /// use vimvar::RawString;
+    ///
+    /// let s = RawString::new(b"caf\xe9".to_vec(), "latin1");
+    /// assert_eq!(s.to_string_lossy(), "café");
+    ///
+    /// let s = RawString::new(b"caf\xe9".to_vec(), "utf-8");
+    /// assert_eq!(s.to_string_lossy(), "caf\u{FFFD}");
+    /// ```
+    pub fn to_string_lossy(&self) -> Cow<'_, str> {
+        if self.is_latin1() {
+            Cow::Owned(self.bytes.iter().map(|b| char::from(*b)).collect())
+        } else {
+            String::from_utf8_lossy(&self.bytes)
+        }
+    }
+
+    /// Converts the bytes into an [`OsString`], which keeps the exact bytes
+    /// on unix and falls back to [`Self::to_string_lossy`] elsewhere
+    pub fn into_os_string(self) -> OsString {
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStringExt;
+            OsString::from_vec(self.bytes)
+        }
+
+        #[cfg(not(unix))]
+        {
+            OsString::from(self.to_string_lossy().into_owned())
+        }
+    }
+}
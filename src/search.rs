@@ -1,3 +1,4 @@
+use crate::Cmd;
 use std::{borrow::Cow, path::PathBuf};
 
 /// Input configuration for finding vimrc
@@ -14,6 +15,24 @@ trait FindVimrcConfig {
     fn vim_env(&self) -> Result<Cow<'static, str>, Self::Err>;
 }
 
+/// Input configuration for building the default runtimepath, extending the
+/// vimrc config with the remaining environment lookups
+trait RuntimepathConfig: FindVimrcConfig {
+    /// Returns $XDG_DATA_HOME path if possible
+    fn xdg_data_home(&self) -> Result<Cow<'static, str>, Self::Err>;
+
+    /// Returns $XDG_CONFIG_DIRS paths (separated by the platform path
+    /// separator) if possible
+    fn xdg_config_dirs(&self) -> Result<Cow<'static, str>, Self::Err>;
+
+    /// Returns $XDG_DATA_DIRS paths (separated by the platform path
+    /// separator) if possible
+    fn xdg_data_dirs(&self) -> Result<Cow<'static, str>, Self::Err>;
+
+    /// Returns $VIMRUNTIME path if possible
+    fn vimruntime_env(&self) -> Result<Cow<'static, str>, Self::Err>;
+}
+
 /// Standard implementation of input for find vimrc config
 struct StandardFindVimrcConfig;
 
@@ -33,6 +52,24 @@ impl FindVimrcConfig for StandardFindVimrcConfig {
     }
 }
 
+impl RuntimepathConfig for StandardFindVimrcConfig {
+    fn xdg_data_home(&self) -> Result<Cow<'static, str>, Self::Err> {
+        shellexpand::env("$XDG_DATA_HOME")
+    }
+
+    fn xdg_config_dirs(&self) -> Result<Cow<'static, str>, Self::Err> {
+        shellexpand::env("$XDG_CONFIG_DIRS")
+    }
+
+    fn xdg_data_dirs(&self) -> Result<Cow<'static, str>, Self::Err> {
+        shellexpand::env("$XDG_DATA_DIRS")
+    }
+
+    fn vimruntime_env(&self) -> Result<Cow<'static, str>, Self::Err> {
+        shellexpand::env("$VIMRUNTIME")
+    }
+}
+
 /// Performs search to find vimrc based on platform, returning first valid
 /// vimrc found. Will check for both `init.vim` and `init.lua` file types.
 ///
@@ -193,6 +230,234 @@ where
     }
 }
 
+/// Builds the default `'runtimepath'` for the given [`Cmd`] natively, without
+/// spawning neovim/vim, so that runtime files (ftplugins, colorschemes, `doc/`
+/// directories, etc) can be located quickly.
+///
+/// ### Neovim
+///
+/// * `$XDG_CONFIG_HOME/nvim` (or `~/.config/nvim`)
+/// * `$XDG_CONFIG_DIRS/nvim` (or `/etc/xdg/nvim`)
+/// * `$XDG_DATA_HOME/nvim/site` (or `~/.local/share/nvim/site`)
+/// * `$XDG_DATA_DIRS/nvim/site` (or `/usr/local/share/nvim/site` and
+///   `/usr/share/nvim/site`)
+/// * `$VIMRUNTIME`
+/// * the `after` directory of each of the above (except `$VIMRUNTIME`) in
+///   reverse order
+///
+/// On Windows, the config and data directories default to
+/// `~/AppData/Local/nvim` and `~/AppData/Local/nvim-data` respectively.
+///
+/// ### Vim
+///
+/// * `~/.vim` (or `~/vimfiles` on Windows)
+/// * `$VIM/vimfiles`
+/// * `$VIMRUNTIME`
+/// * `$VIM/vimfiles/after`
+/// * `~/.vim/after` (or `~/vimfiles/after` on Windows)
+///
+/// ### Notes
+///
+/// * Every `pack/*/start/*` directory that exists beneath an entry is added
+///   directly after that entry, and its `after` directory (if it exists) is
+///   added directly before the entry's own `after` directory
+/// * `$VIMRUNTIME` is taken from the environment if set, otherwise common
+///   install locations are probed and the entry is omitted if none exist
+/// * This reflects the defaults only; plugin managers and user configs can
+///   change the real value, which can be compared against by loading `&rtp`
+///   from the editor itself
+pub fn runtimepath(cmd: Cmd) -> Vec<PathBuf> {
+    runtimepath_impl(cmd, StandardFindVimrcConfig)
+}
+
+/// Builds the default `'packpath'` for the given [`Cmd`] natively, which
+/// consists of the same directories as [`runtimepath`] without any of the
+/// `pack/*/start/*` directories expanded
+pub fn packpath(cmd: Cmd) -> Vec<PathBuf> {
+    packpath_impl(cmd, StandardFindVimrcConfig)
+}
+
+fn runtimepath_impl<C>(cmd: Cmd, config: C) -> Vec<PathBuf>
+where
+    C: RuntimepathConfig,
+{
+    let (bases, afters) = default_path_entries(cmd, &config);
+
+    let mut paths = Vec::new();
+    for base in bases.iter() {
+        paths.push(base.to_path_buf());
+        paths.extend(find_start_packages(base));
+    }
+
+    for after in afters {
+        // NOTE: The after directory of each package is placed ahead of the
+        //       after directory of the entry containing the package
+        if let Some(base) = after.parent() {
+            paths.extend(
+                find_start_packages(base)
+                    .into_iter()
+                    .map(|package| package.join("after"))
+                    .filter(|package_after| package_after.is_dir()),
+            );
+        }
+        paths.push(after);
+    }
+
+    paths
+}
+
+fn packpath_impl<C>(cmd: Cmd, config: C) -> Vec<PathBuf>
+where
+    C: RuntimepathConfig,
+{
+    let (bases, afters) = default_path_entries(cmd, &config);
+    bases.into_iter().chain(afters).collect()
+}
+
+/// Returns the base entries followed by the after entries of the default
+/// runtimepath (excluding any packages)
+fn default_path_entries<C>(cmd: Cmd, config: &C) -> (Vec<PathBuf>, Vec<PathBuf>)
+where
+    C: RuntimepathConfig,
+{
+    let home = config.home();
+    let vim_env = config
+        .vim_env()
+        .ok()
+        .map(|vim| PathBuf::from(vim.as_ref()))
+        .or_else(|| {
+            config
+                .vimruntime_env()
+                .ok()
+                .and_then(|rt| PathBuf::from(rt.as_ref()).parent().map(PathBuf::from))
+        })
+        .or_else(|| {
+            ["/usr/local/share/vim", "/usr/share/vim"]
+                .iter()
+                .map(PathBuf::from)
+                .find(|path| path.is_dir())
+        });
+    let vimruntime = config
+        .vimruntime_env()
+        .ok()
+        .map(|rt| PathBuf::from(rt.as_ref()))
+        .or_else(|| find_vimruntime(cmd, vim_env.as_ref()));
+
+    let mut bases = Vec::new();
+    match cmd {
+        Cmd::Neovim => {
+            let (config_home, data_home, data_dir_name) = if cfg!(windows) {
+                let local = [home.as_ref(), "AppData", "Local"]
+                    .iter()
+                    .collect::<PathBuf>();
+                (local.clone(), local, "nvim-data")
+            } else {
+                (
+                    [home.as_ref(), ".config"].iter().collect::<PathBuf>(),
+                    [home.as_ref(), ".local", "share"]
+                        .iter()
+                        .collect::<PathBuf>(),
+                    "nvim",
+                )
+            };
+            let config_home = config
+                .xdg_config_home()
+                .map(|path| PathBuf::from(path.as_ref()))
+                .unwrap_or(config_home);
+            let data_home = config
+                .xdg_data_home()
+                .map(|path| PathBuf::from(path.as_ref()))
+                .unwrap_or(data_home);
+            let config_dirs = config
+                .xdg_config_dirs()
+                .map(|dirs| std::env::split_paths(dirs.as_ref()).collect::<Vec<_>>())
+                .unwrap_or_else(|_| {
+                    if cfg!(windows) {
+                        Vec::new()
+                    } else {
+                        vec![PathBuf::from("/etc/xdg")]
+                    }
+                });
+            let data_dirs = config
+                .xdg_data_dirs()
+                .map(|dirs| std::env::split_paths(dirs.as_ref()).collect::<Vec<_>>())
+                .unwrap_or_else(|_| {
+                    if cfg!(windows) {
+                        Vec::new()
+                    } else {
+                        vec![
+                            PathBuf::from("/usr/local/share"),
+                            PathBuf::from("/usr/share"),
+                        ]
+                    }
+                });
+
+            bases.push(config_home.join("nvim"));
+            bases.extend(config_dirs.iter().map(|dir| dir.join("nvim")));
+            bases.push(data_home.join(data_dir_name).join("site"));
+            bases.extend(data_dirs.iter().map(|dir| dir.join("nvim").join("site")));
+        }
+        Cmd::Vim => {
+            let user_dir = if cfg!(windows) { "vimfiles" } else { ".vim" };
+            bases.push([home.as_ref(), user_dir].iter().collect::<PathBuf>());
+            bases.extend(vim_env.as_ref().map(|vim| vim.join("vimfiles")));
+        }
+    }
+
+    let afters = bases.iter().rev().map(|base| base.join("after")).collect();
+    bases.extend(vimruntime);
+    (bases, afters)
+}
+
+/// Probes common install locations for the runtime files of neovim/vim
+fn find_vimruntime(cmd: Cmd, vim_env: Option<&PathBuf>) -> Option<PathBuf> {
+    match cmd {
+        Cmd::Neovim => [
+            "/usr/local/share/nvim/runtime",
+            "/usr/share/nvim/runtime",
+            "/opt/homebrew/share/nvim/runtime",
+        ]
+        .iter()
+        .map(PathBuf::from)
+        .find(|path| path.is_dir()),
+
+        // Vim installs its runtime in a versioned directory such as
+        // $VIM/vim90, so we pick the latest one available
+        Cmd::Vim => vim_env
+            .and_then(|vim| std::fs::read_dir(vim).ok())
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_dir())
+            .filter_map(|path| {
+                let version = path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .and_then(|name| name.strip_prefix("vim"))
+                    .and_then(|version| version.parse::<u32>().ok())?;
+                Some((version, path))
+            })
+            .max_by_key(|(version, _)| *version)
+            .map(|(_, path)| path),
+    }
+}
+
+/// Returns every `pack/*/start/*` directory found beneath `base`, sorted by
+/// path
+fn find_start_packages(base: &std::path::Path) -> Vec<PathBuf> {
+    let mut packages: Vec<PathBuf> = std::fs::read_dir(base.join("pack"))
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|entry| entry.path().join("start")))
+        .filter_map(|start| std::fs::read_dir(start).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_dir())
+        .collect();
+    packages.sort();
+    packages
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -203,6 +468,10 @@ mod tests {
         xdg_config_home: Result<Cow<'static, str>, shellexpand::LookupError<std::env::VarError>>,
         home: Cow<'static, str>,
         vim_env: Result<Cow<'static, str>, shellexpand::LookupError<std::env::VarError>>,
+        xdg_data_home: Result<Cow<'static, str>, shellexpand::LookupError<std::env::VarError>>,
+        xdg_config_dirs: Result<Cow<'static, str>, shellexpand::LookupError<std::env::VarError>>,
+        xdg_data_dirs: Result<Cow<'static, str>, shellexpand::LookupError<std::env::VarError>>,
+        vimruntime_env: Result<Cow<'static, str>, shellexpand::LookupError<std::env::VarError>>,
     }
 
     impl FindVimrcConfig for TestFindVimrcConfig {
@@ -221,31 +490,40 @@ mod tests {
         }
     }
 
+    impl RuntimepathConfig for TestFindVimrcConfig {
+        fn xdg_data_home(&self) -> Result<Cow<'static, str>, Self::Err> {
+            self.xdg_data_home.clone()
+        }
+
+        fn xdg_config_dirs(&self) -> Result<Cow<'static, str>, Self::Err> {
+            self.xdg_config_dirs.clone()
+        }
+
+        fn xdg_data_dirs(&self) -> Result<Cow<'static, str>, Self::Err> {
+            self.xdg_data_dirs.clone()
+        }
+
+        fn vimruntime_env(&self) -> Result<Cow<'static, str>, Self::Err> {
+            self.vimruntime_env.clone()
+        }
+    }
+
+    /// Creates an empty directory that outlives the test, used for fake paths
+    fn fake_dir() -> Cow<'static, str> {
+        Cow::Owned(tempdir().unwrap().keep().to_string_lossy().into_owned())
+    }
+
     impl Default for TestFindVimrcConfig {
         /// Create a test config with fake, non-existing paths
         fn default() -> Self {
             Self {
-                xdg_config_home: Ok(Cow::Owned(
-                    tempdir()
-                        .unwrap()
-                        .into_path()
-                        .to_string_lossy()
-                        .into_owned(),
-                )),
-                home: Cow::Owned(
-                    tempdir()
-                        .unwrap()
-                        .into_path()
-                        .to_string_lossy()
-                        .into_owned(),
-                ),
-                vim_env: Ok(Cow::Owned(
-                    tempdir()
-                        .unwrap()
-                        .into_path()
-                        .to_string_lossy()
-                        .into_owned(),
-                )),
+                xdg_config_home: Ok(fake_dir()),
+                home: fake_dir(),
+                vim_env: Ok(fake_dir()),
+                xdg_data_home: Ok(fake_dir()),
+                xdg_config_dirs: Ok(fake_dir()),
+                xdg_data_dirs: Ok(fake_dir()),
+                vimruntime_env: Ok(fake_dir()),
             }
         }
     }
@@ -421,4 +699,136 @@ mod tests {
 
         assert_eq!(find_vimrc_impl(config), Some(config_file));
     }
+
+    fn cow_to_path_buf(
+        cow: &Result<Cow<'static, str>, shellexpand::LookupError<std::env::VarError>>,
+    ) -> PathBuf {
+        PathBuf::from(cow.as_ref().unwrap().as_ref())
+    }
+
+    #[test]
+    fn runtimepath_for_neovim_should_include_config_data_and_vimruntime_dirs() {
+        let config = TestFindVimrcConfig::default();
+        let config_home = cow_to_path_buf(&config.xdg_config_home);
+        let config_dirs = cow_to_path_buf(&config.xdg_config_dirs);
+        let data_home = cow_to_path_buf(&config.xdg_data_home);
+        let data_dirs = cow_to_path_buf(&config.xdg_data_dirs);
+        let vimruntime = cow_to_path_buf(&config.vimruntime_env);
+
+        assert_eq!(
+            runtimepath_impl(Cmd::Neovim, config),
+            vec![
+                config_home.join("nvim"),
+                config_dirs.join("nvim"),
+                data_home.join("nvim").join("site"),
+                data_dirs.join("nvim").join("site"),
+                vimruntime,
+                data_dirs.join("nvim").join("site").join("after"),
+                data_home.join("nvim").join("site").join("after"),
+                config_dirs.join("nvim").join("after"),
+                config_home.join("nvim").join("after"),
+            ]
+        );
+    }
+
+    #[test]
+    fn runtimepath_for_vim_should_include_home_vimfiles_and_vimruntime_dirs() {
+        let config = TestFindVimrcConfig::default();
+        let user_dir = PathBuf::from(config.home.as_ref()).join(if cfg!(windows) {
+            "vimfiles"
+        } else {
+            ".vim"
+        });
+        let vimfiles = cow_to_path_buf(&config.vim_env).join("vimfiles");
+        let vimruntime = cow_to_path_buf(&config.vimruntime_env);
+
+        assert_eq!(
+            runtimepath_impl(Cmd::Vim, config),
+            vec![
+                user_dir.clone(),
+                vimfiles.clone(),
+                vimruntime,
+                vimfiles.join("after"),
+                user_dir.join("after"),
+            ]
+        );
+    }
+
+    #[test]
+    fn runtimepath_should_include_start_packages_and_their_after_dirs() {
+        let root = tempdir().unwrap();
+        create_file(
+            root.as_ref(),
+            &[
+                "nvim", "site", "pack", "a", "start", "one", "plugin", "one.vim",
+            ],
+        );
+        create_file(
+            root.as_ref(),
+            &[
+                "nvim", "site", "pack", "a", "start", "two", "after", "plugin", "two.vim",
+            ],
+        );
+        create_file(
+            root.as_ref(),
+            &[
+                "nvim",
+                "site",
+                "pack",
+                "a",
+                "opt",
+                "three",
+                "plugin",
+                "three.vim",
+            ],
+        );
+
+        let config = TestFindVimrcConfig {
+            xdg_data_home: Ok(tempdir_to_cow_str(&root)),
+            ..Default::default()
+        };
+        let site = root.as_ref().join("nvim").join("site");
+        let start = site.join("pack").join("a").join("start");
+
+        let paths = runtimepath_impl(Cmd::Neovim, config);
+        let site_idx = paths.iter().position(|p| p == &site).unwrap();
+        assert_eq!(paths[site_idx + 1], start.join("one"));
+        assert_eq!(paths[site_idx + 2], start.join("two"));
+
+        let after_idx = paths.iter().position(|p| p == &site.join("after")).unwrap();
+        assert_eq!(paths[after_idx - 1], start.join("two").join("after"));
+        assert!(!paths
+            .iter()
+            .any(|p| p.starts_with(site.join("pack").join("a").join("opt"))));
+    }
+
+    #[test]
+    fn packpath_should_not_include_packages() {
+        let root = tempdir().unwrap();
+        create_file(
+            root.as_ref(),
+            &[".vim", "pack", "a", "start", "one", "plugin", "one.vim"],
+        );
+
+        let config = TestFindVimrcConfig {
+            home: tempdir_to_cow_str(&root),
+            ..Default::default()
+        };
+        let vimfiles = cow_to_path_buf(&config.vim_env).join("vimfiles");
+        let vimruntime = cow_to_path_buf(&config.vimruntime_env);
+        let user_dir = root
+            .as_ref()
+            .join(if cfg!(windows) { "vimfiles" } else { ".vim" });
+
+        assert_eq!(
+            packpath_impl(Cmd::Vim, config),
+            vec![
+                user_dir.clone(),
+                vimfiles.clone(),
+                vimruntime,
+                vimfiles.join("after"),
+                user_dir.join("after"),
+            ]
+        );
+    }
 }
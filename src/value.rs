@@ -0,0 +1,428 @@
+use serde::{
+    de::{self, DeserializeOwned, Deserializer},
+    ser::{Serialize, SerializeMap, SerializeSeq, Serializer},
+    Deserialize,
+};
+use serde_json::Value;
+use std::{collections::BTreeMap, fmt, io, str::FromStr};
+
+/// Placeholder loaded in place of a value that cannot be encoded as JSON, such
+/// as a Funcref stored as a callback within a plugin's config Dict or a
+/// non-finite Float
+///
+/// ### Examples
+///
+/// ```
+/// use serde::Deserialize;
+/// use serde_json::json;
+/// use vimvar::{Unencodable, VimType};
+///
+/// #[derive(Deserialize)]
+/// struct Config {
+///     width: u32,
+///     on_attach: Option<Unencodable>,
+/// }
+///
+/// let config: Config = serde_json::from_value(json!({
+///     "width": 80,
+///     "on_attach": {
+///         "__vimvar_unencodable__": "funcref",
+///         "repr": "function('<lambda>1')",
+///     },
+/// })).unwrap();
+///
+/// let on_attach = config.on_attach.unwrap();
+/// assert_eq!(on_attach.kind, VimType::Funcref);
+/// assert_eq!(on_attach.repr, "function('<lambda>1')");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, Deserialize)]
+pub struct Unencodable {
+    /// Type of the value that could not be encoded
+    #[serde(rename = "__vimvar_unencodable__")]
+    pub kind: VimType,
+
+    /// Representation of the value produced by `string()`
+    pub repr: String,
+}
+
+/// Represents a value loaded from neovim/vim that preserves its Vimscript type
+#[derive(Clone, Debug, PartialEq)]
+pub enum VimValue {
+    /// Integer value (`42`)
+    Number(i64),
+
+    /// Floating point value (`1.5`), including `inf` and `nan`
+    Float(f64),
+
+    /// Text value (`'text'`)
+    String(String),
+
+    /// Ordered collection of values (`[1, 2]`)
+    List(Vec<VimValue>),
+
+    /// Collection of values keyed by strings (`{'a': 1}`)
+    Dict(BTreeMap<String, VimValue>),
+
+    /// Binary data (`0z0102`)
+    Blob(Vec<u8>),
+
+    /// Reference to a function (`function('Name')`), partial or lambda, holding
+    /// the name of the function
+    Funcref(String),
+
+    /// Boolean value (`v:true` or `v:false`)
+    Bool(bool),
+
+    /// Special value `v:null`
+    Null,
+
+    /// Special value `v:none`
+    None,
+
+    /// Any other value, such as a job or channel, holding its kind and its
+    /// representation produced by `string()`
+    Special { kind: VimType, repr: String },
+}
+
+impl VimValue {
+    /// Returns the [`VimType`] of the value
+    ///
+    /// ### Examples
+    ///
+    /// ```
+    /// use vimvar::{VimType, VimValue};
+    ///
+    /// assert_eq!(VimValue::Number(1).kind(), VimType::Number);
+    /// assert_eq!(VimValue::Null.kind(), VimType::Special);
+    /// ```
+    pub fn kind(&self) -> VimType {
+        match self {
+            Self::Number(_) => VimType::Number,
+            Self::Float(_) => VimType::Float,
+            Self::String(_) => VimType::String,
+            Self::List(_) => VimType::List,
+            Self::Dict(_) => VimType::Dict,
+            Self::Blob(_) => VimType::Blob,
+            Self::Funcref(_) => VimType::Funcref,
+            Self::Bool(_) => VimType::Bool,
+            Self::Null | Self::None => VimType::Special,
+            Self::Special { kind, .. } => *kind,
+        }
+    }
+
+    /// Converts the value into the specified type, returning an [`io::Error`]
+    /// if failing to convert
+    ///
+    /// ### Notes
+    ///
+    /// * Blobs convert into a sequence of bytes
+    /// * Funcrefs convert into the name of the function
+    /// * `v:null` and `v:none` convert into unit (and `None`)
+    /// * Specials convert into their representation produced by `string()`
+    /// * Errors include the path of the value that failed to convert, such as
+    ///   `servers[3].port`
+    pub fn into_typed<T>(self) -> io::Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        crate::de::deserialize(self, "")
+    }
+
+    /// Creates a value from the JSON produced by the editor's tagged encoder,
+    /// where each value is a Dict of `t` (the result of `type()`) and `v` (the
+    /// value encoded in a form that JSON can represent)
+    pub(crate) fn from_tagged(tagged: Value) -> io::Result<Self> {
+        fn invalid(msg: impl Into<String>) -> io::Error {
+            io::Error::new(io::ErrorKind::InvalidData, msg.into())
+        }
+
+        let (kind, value) = match tagged {
+            Value::Object(mut map) => match (map.remove("t"), map.remove("v")) {
+                (Some(Value::Number(t)), Some(v)) => {
+                    (VimType::from_type_number(t.as_i64().unwrap_or(-1)), v)
+                }
+                _ => return Err(invalid("Tagged value is missing its type or value")),
+            },
+            x => return Err(invalid(format!("Expected tagged value, but got {}", x))),
+        };
+
+        Ok(match (kind, value) {
+            (VimType::Number, Value::Number(n)) => Self::Number(
+                n.as_i64()
+                    .ok_or_else(|| invalid(format!("Number {} out of range", n)))?,
+            ),
+            (VimType::Float, Value::String(s)) => Self::Float(
+                s.parse()
+                    .map_err(|_| invalid(format!("Invalid Float: {}", s)))?,
+            ),
+            (VimType::String, Value::String(s)) => Self::String(s),
+            (VimType::List, Value::Array(items)) => Self::List(
+                items
+                    .into_iter()
+                    .map(Self::from_tagged)
+                    .collect::<io::Result<_>>()?,
+            ),
+            (VimType::Dict, Value::Object(map)) => Self::Dict(
+                map.into_iter()
+                    .map(|(k, v)| Ok((k, Self::from_tagged(v)?)))
+                    .collect::<io::Result<_>>()?,
+            ),
+            (VimType::Blob, v @ Value::Array(_)) => Self::Blob(serde_json::from_value(v)?),
+            (VimType::Funcref, Value::String(name)) => Self::Funcref(name),
+            (VimType::Bool, Value::Bool(b)) => Self::Bool(b),
+            (VimType::Special, Value::String(s)) if s == "v:null" => Self::Null,
+            (VimType::Special, Value::String(s)) if s == "v:none" => Self::None,
+            (
+                kind @ (VimType::Special | VimType::Job | VimType::Channel | VimType::Other(_)),
+                Value::String(repr),
+            ) => Self::Special { kind, repr },
+            (kind, v) => return Err(invalid(format!("Invalid {}: {}", kind, v))),
+        })
+    }
+}
+
+impl From<VimValue> for Value {
+    /// Converts into JSON, where non-finite floats become null and the other
+    /// types follow the conversions described in [`VimValue::into_typed`]
+    fn from(value: VimValue) -> Self {
+        match value {
+            VimValue::Number(n) => Value::from(n),
+            VimValue::Float(f) => serde_json::Number::from_f64(f)
+                .map(Value::Number)
+                .unwrap_or(Value::Null),
+            VimValue::String(s) => Value::String(s),
+            VimValue::List(items) => Value::Array(items.into_iter().map(Value::from).collect()),
+            VimValue::Dict(map) => {
+                Value::Object(map.into_iter().map(|(k, v)| (k, Value::from(v))).collect())
+            }
+            VimValue::Blob(bytes) => Value::from(bytes),
+            VimValue::Funcref(name) => Value::String(name),
+            VimValue::Bool(b) => Value::Bool(b),
+            VimValue::Null | VimValue::None => Value::Null,
+            VimValue::Special { repr, .. } => Value::String(repr),
+        }
+    }
+}
+
+impl Serialize for VimValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Number(n) => serializer.serialize_i64(*n),
+            Self::Float(f) => serializer.serialize_f64(*f),
+            Self::String(s) => serializer.serialize_str(s),
+            Self::List(items) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            Self::Dict(map) => {
+                let mut ser = serializer.serialize_map(Some(map.len()))?;
+                for (k, v) in map {
+                    ser.serialize_entry(k, v)?;
+                }
+                ser.end()
+            }
+            Self::Blob(bytes) => serializer.serialize_bytes(bytes),
+            Self::Funcref(name) => serializer.serialize_str(name),
+            Self::Bool(b) => serializer.serialize_bool(*b),
+            Self::Null | Self::None => serializer.serialize_unit(),
+            Self::Special { repr, .. } => serializer.serialize_str(repr),
+        }
+    }
+}
+
+/// Represents the type of a value as reported by `type()` in neovim/vim
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum VimType {
+    Number,
+    String,
+    Funcref,
+    List,
+    Dict,
+    Float,
+    Bool,
+    Special,
+    Job,
+    Channel,
+    Blob,
+    Other(i64),
+}
+
+impl VimType {
+    /// Converts the number returned by `type()` into a type
+    ///
+    /// ### Examples
+    ///
+    /// ```
+    /// use vimvar::VimType;
+    ///
+    /// assert_eq!(VimType::from_type_number(0), VimType::Number);
+    /// assert_eq!(VimType::from_type_number(10), VimType::Blob);
+    /// assert_eq!(VimType::from_type_number(99), VimType::Other(99));
+    /// ```
+    pub fn from_type_number(n: i64) -> Self {
+        match n {
+            0 => Self::Number,
+            1 => Self::String,
+            2 => Self::Funcref,
+            3 => Self::List,
+            4 => Self::Dict,
+            5 => Self::Float,
+            6 => Self::Bool,
+            7 => Self::Special,
+            8 => Self::Job,
+            9 => Self::Channel,
+            10 => Self::Blob,
+            n => Self::Other(n),
+        }
+    }
+
+    /// Converts to a str representing type
+    ///
+    /// ### Examples
+    ///
+    /// ```
+    /// use vimvar::VimType;
+    ///
+    /// assert_eq!(VimType::Number.as_str(), "number");
+    /// assert_eq!(VimType::Dict.as_str(), "dict");
+    /// assert_eq!(VimType::Other(99).as_str(), "other");
+    /// ```
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Number => "number",
+            Self::String => "string",
+            Self::Funcref => "funcref",
+            Self::List => "list",
+            Self::Dict => "dict",
+            Self::Float => "float",
+            Self::Bool => "bool",
+            Self::Special => "special",
+            Self::Job => "job",
+            Self::Channel => "channel",
+            Self::Blob => "blob",
+            Self::Other(_) => "other",
+        }
+    }
+}
+
+impl fmt::Display for VimType {
+    /// Writes type using the [`Self::as_str`] representation
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for VimType {
+    type Err = io::Error;
+
+    /// Parses type from the [`Self::as_str`] representation, where an unknown
+    /// name results in [`VimType::Other`]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "number" => Self::Number,
+            "string" => Self::String,
+            "funcref" => Self::Funcref,
+            "list" => Self::List,
+            "dict" => Self::Dict,
+            "float" => Self::Float,
+            "bool" => Self::Bool,
+            "special" => Self::Special,
+            "job" => Self::Job,
+            "channel" => Self::Channel,
+            "blob" => Self::Blob,
+            _ => Self::Other(-1),
+        })
+    }
+}
+
+impl Serialize for VimType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for VimType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn from_tagged_should_support_scalars() {
+        let value = |t, v| VimValue::from_tagged(json!({ "t": t, "v": v })).unwrap();
+
+        assert_eq!(value(0, json!(42)), VimValue::Number(42));
+        assert_eq!(value(1, json!("text")), VimValue::String("text".into()));
+        assert_eq!(
+            value(2, json!("MyFunc")),
+            VimValue::Funcref("MyFunc".into())
+        );
+        assert_eq!(value(5, json!("1.5")), VimValue::Float(1.5));
+        assert_eq!(value(5, json!("-inf")), VimValue::Float(f64::NEG_INFINITY));
+        assert_eq!(value(6, json!(true)), VimValue::Bool(true));
+        assert_eq!(value(7, json!("v:null")), VimValue::Null);
+        assert_eq!(value(7, json!("v:none")), VimValue::None);
+        assert_eq!(value(10, json!([0, 255])), VimValue::Blob(vec![0, 255]));
+        assert_eq!(
+            value(8, json!("process 123 run")),
+            VimValue::Special {
+                kind: VimType::Job,
+                repr: "process 123 run".into()
+            }
+        );
+    }
+
+    #[test]
+    fn from_tagged_should_support_nan() {
+        let value = VimValue::from_tagged(json!({ "t": 5, "v": "nan" })).unwrap();
+        assert!(matches!(value, VimValue::Float(f) if f.is_nan()));
+    }
+
+    #[test]
+    fn from_tagged_should_support_nested_collections() {
+        let value = VimValue::from_tagged(json!({
+            "t": 4,
+            "v": {
+                "list": { "t": 3, "v": [{ "t": 0, "v": 1 }, { "t": 6, "v": false }] },
+                "empty": { "t": 4, "v": {} },
+            }
+        }))
+        .unwrap();
+
+        let mut map = BTreeMap::new();
+        map.insert(
+            "list".to_string(),
+            VimValue::List(vec![VimValue::Number(1), VimValue::Bool(false)]),
+        );
+        map.insert("empty".to_string(), VimValue::Dict(BTreeMap::new()));
+        assert_eq!(value, VimValue::Dict(map));
+    }
+
+    #[test]
+    fn from_tagged_should_fail_if_value_does_not_match_type() {
+        assert!(VimValue::from_tagged(json!({ "t": 0, "v": "text" })).is_err());
+        assert!(VimValue::from_tagged(json!({ "v": 1 })).is_err());
+        assert!(VimValue::from_tagged(json!(1)).is_err());
+    }
+
+    #[test]
+    fn into_typed_should_convert_into_serde_types() {
+        let value = VimValue::List(vec![
+            VimValue::Funcref("MyFunc".into()),
+            VimValue::Null,
+            VimValue::Blob(vec![1, 2]),
+        ]);
+
+        let typed: (String, Option<u8>, Vec<u8>) = value.into_typed().unwrap();
+        assert_eq!(typed, ("MyFunc".to_string(), None, vec![1, 2]));
+    }
+}
@@ -1,7 +1,7 @@
-use crate::search;
+use crate::{process, search, Editor, VimType, VimValue};
 use serde::de::DeserializeOwned;
 use serde_json::Value;
-use std::{fmt, io, path::Path, process::Command};
+use std::{fmt, io, path::Path};
 
 /// Represents a vim variable to be extracted
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -14,6 +14,15 @@ pub struct VimVar<Name: AsRef<str>> {
 impl<Name: AsRef<str>> VimVar<Name> {
     /// Creates a new vim variable definition that can be used later to
     /// load the variable's contents
+    ///
+    /// ### Notes
+    ///
+    /// * `name` may be followed by a key path into nested Dicts and Lists,
+    ///   such as `lsp_settings.rust.cargo.features` or
+    ///   `foo['key with spaces'][2]`, in which case only the value at the end
+    ///   of the path is loaded
+    /// * A key missing at any point along the path is treated the same as a
+    ///   missing variable
     pub fn new(cmd: Cmd, scope: Scope, name: Name) -> Self {
         Self { cmd, scope, name }
     }
@@ -42,13 +51,15 @@ impl<Name: AsRef<str>> VimVar<Name> {
     ///
     /// * If `allow_zero` is true, then a value of 0 is considered the value of
     ///   the variable rather than vim's default of not being found
+    /// * Conversion errors include the path of the value that failed within
+    ///   the variable, such as `g:myplugin.servers[3].port`
     pub fn load_typed<T>(&self, allow_zero: bool) -> io::Result<Option<T>>
     where
         T: DeserializeOwned,
     {
-        self.load(allow_zero)?
-            .map(|value| serde_json::from_value(value).map_err(Into::into))
-            .transpose()
+        let vimrc = search::find_vimrc()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "vimrc not found"))?;
+        self.load_typed_with_config(vimrc, allow_zero)
     }
 
     /// Loads the variable's value using neovim's headless mode or vim's ex
@@ -60,12 +71,49 @@ impl<Name: AsRef<str>> VimVar<Name> {
     ///   during ex mode
     /// * If `allow_zero` is true, then a value of 0 is considered the value of
     ///   the variable rather than vim's default of not being found
+    /// * Returns an [`io::Error`] if the name contains an invalid key path
     pub fn load(&self, allow_zero: bool) -> io::Result<Option<Value>> {
         let vimrc = search::find_vimrc()
             .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "vimrc not found"))?;
         self.load_with_config(vimrc, allow_zero)
     }
 
+    /// Loads the variable's value as a [`VimValue`], which preserves the
+    /// Vimscript type of the value, using the default vimrc available in scope
+    ///
+    /// ### Notes
+    ///
+    /// * Will leverage [`search::find_vimrc`] to load in the appropriate vimrc
+    ///   during ex mode
+    /// * If `allow_zero` is true, then a value of 0 is considered the value of
+    ///   the variable rather than vim's default of not being found
+    pub fn load_value(&self, allow_zero: bool) -> io::Result<Option<VimValue>> {
+        let vimrc = search::find_vimrc()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "vimrc not found"))?;
+        self.load_value_with_config(vimrc, allow_zero)
+    }
+
+    /// Loads the variable's value as a [`VimValue`], which preserves the
+    /// Vimscript type of the value
+    ///
+    /// ### Notes
+    ///
+    /// * If `allow_zero` is true, then a value of 0 is considered the value of
+    ///   the variable rather than vim's default of not being found
+    pub fn load_value_with_config<P: AsRef<Path>>(
+        &self,
+        config: P,
+        allow_zero: bool,
+    ) -> io::Result<Option<VimValue>> {
+        let value = Editor::new(self.cmd, config.as_ref()).eval_value(&self.get_expr()?)?;
+
+        if !allow_zero && value == VimValue::Number(0) {
+            Ok(None)
+        } else {
+            Ok(Some(value))
+        }
+    }
+
     /// Loads variable with [`Self::load_with_config`] and then attempts to
     /// convert it to the specified type
     ///
@@ -73,6 +121,8 @@ impl<Name: AsRef<str>> VimVar<Name> {
     ///
     /// * If `allow_zero` is true, then a value of 0 is considered the value of
     ///   the variable rather than vim's default of not being found
+    /// * Conversion errors include the path of the value that failed within
+    ///   the variable, such as `g:myplugin.servers[3].port`
     pub fn load_typed_with_config<P: AsRef<Path>, T>(
         &self,
         config: P,
@@ -81,9 +131,16 @@ impl<Name: AsRef<str>> VimVar<Name> {
     where
         T: DeserializeOwned,
     {
-        self.load_with_config(config, allow_zero)?
-            .map(|value| serde_json::from_value(value).map_err(Into::into))
-            .transpose()
+        // A value of 0 is filtered out by the editor so that the value can be
+        // converted directly into the specified type as it is read
+        let expr = self.get_expr()?;
+        let expr = if allow_zero {
+            format!("[{}]", expr)
+        } else {
+            format!("{{x -> type(x) == type(0) && x == 0 ? [] : [x]}}({})", expr)
+        };
+
+        Editor::new(self.cmd, config.as_ref()).eval_found(&expr, &self.full_name())
     }
 
     /// Loads the variable's value using neovim's headless mode or vim's ex
@@ -93,7 +150,8 @@ impl<Name: AsRef<str>> VimVar<Name> {
     ///
     /// * Spawns a vim process whose goal is to print out the contents of a
     ///   variable as a JSON string
-    /// * Leverages batch & ex modes with redir to execute and capture output
+    /// * Leverages batch & ex modes, writing the result to a temporary file so
+    ///   that other output (such as from plugins) is ignored
     /// * Relies on the variable being available upon loading vim configs
     /// * If `allow_zero` is true, then a value of 0 is considered the value of
     ///   the variable rather than vim's default of not being found
@@ -102,88 +160,132 @@ impl<Name: AsRef<str>> VimVar<Name> {
         config: P,
         allow_zero: bool,
     ) -> io::Result<Option<Value>> {
-        let cmd = self.cmd;
-        let scope = self.scope.as_str();
-        let var = self.name.as_ref();
-
-        let full_cmd = {
-            if config.as_ref().as_os_str().is_empty() {
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidInput,
-                    "path to vimrc is required for neovim/vim",
-                ));
-            }
-
-            // NOTE: We have a lot of settings being applied, so documenting
-            //       them here
-            //
-            //       1. -Es is our silent, batch, ex mode
-            //       2. -i NONE removes shada/viminfo file reading and writing
-            //       3. -u "{}" loads our vimrc, which is required as -Es does
-            //          not load vim scripts by default
-            //       4. +set nonumber is used to turn off line numbers, which
-            //          are getting picked up by neovim/vim in vimrc configs
-            //          and showing up in output
-            //       5. redir writes to a register our message (json) and then
-            //          places it in our buffer
-            //       6. prints out the content in our buffer (current line)
-            format!(
-                r#"{} -Es -i NONE -u "{}" '+set nonumber' '+redir => m | echon json_encode(get({}, "{}")) | redir END | put=m' '+%p' '+qa!'"#,
-                cmd,
-                config.as_ref().to_string_lossy(),
-                scope,
-                var,
-            )
-        };
+        let value: Value = Editor::new(self.cmd, config.as_ref()).eval(&self.get_expr()?)?;
 
-        // TODO: Support windows here (won't have sh)
-        let output = Command::new("sh").arg("-c").arg(full_cmd).output()?;
-
-        // If our program failed, we want to report the failure
-        //
-        // NOTE: neovim/vim seems to return exit code 1; so, for now we'll
-        //       ignore that specific exit code for now
-        if !output.status.success() && (output.status.code() != Some(1)) {
-            let code = output
-                .status
-                .code()
-                .as_ref()
-                .map(ToString::to_string)
-                .unwrap_or_else(|| String::from("--"));
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!(
-                    "[Exit code {}]: {}",
-                    code,
-                    String::from_utf8_lossy(&output.stderr).trim()
-                )
-                .as_str(),
-            ));
+        if !allow_zero && value == serde_json::json!(0) {
+            Ok(None)
+        } else {
+            Ok(Some(value))
         }
+    }
 
-        let output_string = String::from_utf8_lossy(&output.stdout);
+    /// Returns the name of the variable including its scope, such as `g:foo`
+    fn full_name(&self) -> String {
+        format!("{}{}", self.scope.as_str(), self.name.as_ref())
+    }
 
-        // Report a better error than the serde one if the output was empty
-        if output_string.trim().is_empty() {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!("Result from {} was empty", self.cmd),
-            ));
+    /// Returns the expression used to retrieve the variable from its scope,
+    /// where a key path is followed using `s:walk()` so that a missing key
+    /// (or a key within a value that cannot contain it) results in 0 rather
+    /// than an exception
+    fn get_expr(&self) -> io::Result<String> {
+        let (name, path) = parse_name(self.name.as_ref())?;
+        let expr = format!(
+            "get({}, {})",
+            self.scope.as_str(),
+            process::vim_string_literal(name)
+        );
+        if path.is_empty() {
+            return Ok(expr);
         }
 
-        let value: Value = serde_json::from_str(output_string.trim()).map_err(|_| {
-            io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!("Failed to parse as JSON: \"{}\"", output_string.trim()),
-            )
-        })?;
+        let path: Vec<String> = path
+            .into_iter()
+            .map(|key| match key {
+                PathKey::Key(key) => process::vim_string_literal(&key),
+                PathKey::Index(index) => index.to_string(),
+            })
+            .collect();
+        Ok(format!("s:walk({}, [{}])", expr, path.join(", ")))
+    }
+}
 
-        if !allow_zero && value == serde_json::json!(0) {
-            Ok(None)
+/// Represents a single step within the key path of a variable's name
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum PathKey {
+    /// Key within a Dict (`.key` or `['key']`)
+    Key(String),
+
+    /// Index within a List (`[2]`)
+    Index(i64),
+}
+
+/// Splits a name such as `foo.bar['key with spaces'][2]` into the name of the
+/// variable and the key path that follows it
+fn parse_name(name: &str) -> io::Result<(&str, Vec<PathKey>)> {
+    fn invalid(name: &str, msg: &str) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid variable name \"{}\": {}", name, msg),
+        )
+    }
+
+    let end = name.find(['.', '[']).unwrap_or(name.len());
+    if end == 0 {
+        return Err(invalid(name, "missing variable name"));
+    }
+
+    let mut path = Vec::new();
+    let mut rest = &name[end..];
+    while !rest.is_empty() {
+        if let Some(tail) = rest.strip_prefix('.') {
+            let end = tail
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(tail.len());
+            if end == 0 {
+                return Err(invalid(name, "expected key after '.'"));
+            }
+            path.push(PathKey::Key(tail[..end].to_string()));
+            rest = &tail[end..];
+        } else if let Some(tail) = rest.strip_prefix("['") {
+            // Within single quotes, '' represents a literal quote
+            let mut key = String::new();
+            let mut chars = tail.char_indices().peekable();
+            let end = loop {
+                match chars.next() {
+                    Some((_, '\'')) if chars.peek().map(|(_, c)| *c) == Some('\'') => {
+                        chars.next();
+                        key.push('\'');
+                    }
+                    Some((i, '\'')) => break i,
+                    Some((_, c)) => key.push(c),
+                    None => return Err(invalid(name, "unterminated quoted key")),
+                }
+            };
+            rest = tail[end + 1..]
+                .strip_prefix(']')
+                .ok_or_else(|| invalid(name, "expected ']' after quoted key"))?;
+            path.push(PathKey::Key(key));
+        } else if let Some(tail) = rest.strip_prefix('[') {
+            let end = tail
+                .find(']')
+                .ok_or_else(|| invalid(name, "expected ']' after index"))?;
+            let index = tail[..end]
+                .trim()
+                .parse()
+                .map_err(|_| invalid(name, "expected number or quoted key within []"))?;
+            path.push(PathKey::Index(index));
+            rest = &tail[end + 1..];
         } else {
-            Ok(Some(value))
+            return Err(invalid(name, "expected '.' or '[' after key"));
         }
     }
+
+    Ok((&name[..end], path))
+}
+
+/// Represents a variable found within a scope by [`crate::Editor::list_vars`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct VarInfo {
+    /// Name of the variable without its scope (such as `myplugin_enabled`)
+    pub name: String,
+
+    /// Vimscript type of the variable's value
+    pub kind: VimType,
+
+    /// Value of the variable, only populated by
+    /// [`crate::Editor::list_vars_with_values`]
+    pub value: Option<VimValue>,
 }
 
 /// Represents type of vim instance being used
@@ -279,3 +381,69 @@ impl fmt::Display for Scope {
         write!(f, "{}", self.as_str())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key: &str) -> PathKey {
+        PathKey::Key(key.to_string())
+    }
+
+    #[test]
+    fn parse_name_should_support_plain_names() {
+        assert_eq!(parse_name("foo").unwrap(), ("foo", vec![]));
+        assert_eq!(parse_name("plugin#var").unwrap(), ("plugin#var", vec![]));
+    }
+
+    #[test]
+    fn parse_name_should_support_dotted_keys() {
+        assert_eq!(
+            parse_name("lsp_settings.rust.cargo.features").unwrap(),
+            (
+                "lsp_settings",
+                vec![key("rust"), key("cargo"), key("features")]
+            )
+        );
+    }
+
+    #[test]
+    fn parse_name_should_support_quoted_keys_and_indexes() {
+        assert_eq!(
+            parse_name("foo['key with spaces'][2]['it''s'][-1]").unwrap(),
+            (
+                "foo",
+                vec![
+                    key("key with spaces"),
+                    PathKey::Index(2),
+                    key("it's"),
+                    PathKey::Index(-1)
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn parse_name_should_fail_if_path_is_invalid() {
+        assert!(parse_name("").is_err());
+        assert!(parse_name(".foo").is_err());
+        assert!(parse_name("foo.").is_err());
+        assert!(parse_name("foo['bar").is_err());
+        assert!(parse_name("foo['bar'").is_err());
+        assert!(parse_name("foo[bar]").is_err());
+        assert!(parse_name("foo[1").is_err());
+        assert!(parse_name("foo[1]bar").is_err());
+    }
+
+    #[test]
+    fn get_expr_should_walk_key_path() {
+        let var = VimVar::new(Cmd::Vim, Scope::Global, "foo");
+        assert_eq!(var.get_expr().unwrap(), "get(g:, 'foo')");
+
+        let var = VimVar::new(Cmd::Vim, Scope::Global, "foo.bar['it''s'][2]");
+        assert_eq!(
+            var.get_expr().unwrap(),
+            "s:walk(get(g:, 'foo'), ['bar', 'it''s', 2])"
+        );
+    }
+}
@@ -1,5 +1,5 @@
-use serde_json::json;
-use tempfile::{NamedTempFile, TempPath};
+use serde_json::{json, Value};
+use tempfile::{NamedTempFile, TempDir, TempPath};
 use vimvar::*;
 
 static TEST_VIMRC: &str = r#"
@@ -7,15 +7,44 @@ let b:my_buffer_var = 'some buffer value'
 let w:my_window_var = 'some window value'
 let t:my_tabpage_var = 'some tabpage value'
 let g:my_global_var = 'some global value'
+let g:my_dict_var = {'list': [1, 2, 3], 'quoted': "it's \"here\""}
+
+function! MyEcho(...) abort
+  return a:000
+endfunction
+"#;
+
+/// Config that prints to stdout while being loaded, which should not interfere
+/// with the results
+static NOISY_VIMRC: &str = r#"
+set number
+put ='noise from a plugin'
+print
+let g:my_noisy_var = 42
 "#;
 
 fn make_test_vimrc() -> TempPath {
+    make_vimrc(TEST_VIMRC)
+}
+
+fn make_vimrc(contents: &str) -> TempPath {
     use std::io::Write;
     let mut file = NamedTempFile::new().unwrap();
-    file.as_file_mut().write_all(TEST_VIMRC.as_bytes()).unwrap();
+    file.as_file_mut().write_all(contents.as_bytes()).unwrap();
     file.into_temp_path()
 }
 
+/// Creates a directory containing `colors/vimvar_test.vim` and a vimrc that
+/// prepends that directory to the runtimepath
+fn make_test_runtime() -> (TempDir, TempPath) {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("colors")).unwrap();
+    std::fs::write(dir.path().join("colors").join("vimvar_test.vim"), "").unwrap();
+
+    let path = make_vimrc(&format!("set rtp^={}\n", dir.path().to_string_lossy()));
+    (dir, path)
+}
+
 macro_rules! impl_tests {
     ($cmd:expr) => {
         #[test]
@@ -87,15 +116,1093 @@ macro_rules! impl_tests {
 
             assert_eq!(result.is_err(), true);
         }
+
+        #[test]
+        fn can_load_value_at_key_path_within_variable() {
+            let path = make_test_vimrc();
+
+            let var = VimVar::new($cmd, Scope::Global, "my_dict_var.list[1]");
+            let value = var
+                .load_typed_with_config::<_, u8>(&path, false)
+                .expect("Failed to load variable");
+            assert_eq!(value, Some(2));
+
+            let var = VimVar::new($cmd, Scope::Global, "my_dict_var['quoted']");
+            let value = var
+                .load_typed_with_config::<_, String>(&path, false)
+                .expect("Failed to load variable");
+            assert_eq!(value.as_deref(), Some("it's \"here\""));
+        }
+
+        #[test]
+        fn key_path_with_missing_key_is_treated_as_missing() {
+            let path = make_test_vimrc();
+
+            for name in &[
+                "my_dict_var.missing.deeper",
+                "missing_var['a b'][0]",
+                "my_dict_var.list[5]",
+            ] {
+                let var = VimVar::new($cmd, Scope::Global, name);
+                let value = var
+                    .load_with_config(&path, false)
+                    .expect("Failed to load variable");
+                assert_eq!(value, None, "{} was not missing", name);
+            }
+        }
+
+        #[test]
+        fn key_path_into_value_that_cannot_contain_it_is_treated_as_missing() {
+            let path = make_vimrc(concat!(
+                "let g:list_var = [7, 8]\n",
+                "let g:string_var = 'abc'\n",
+                "let g:dict_var = {'blob': 0z0102}\n",
+            ));
+
+            for name in &[
+                "list_var.key",
+                "string_var.key",
+                "string_var[0]",
+                "dict_var[0]",
+                "dict_var.blob.key",
+            ] {
+                let var = VimVar::new($cmd, Scope::Global, name);
+                let value = var
+                    .load_with_config(&path, false)
+                    .expect("Failed to load variable");
+                assert_eq!(value, None, "{} was not missing", name);
+            }
+
+            let var = VimVar::new($cmd, Scope::Global, "dict_var.blob[1]");
+            let value = var
+                .load_typed_with_config::<_, u8>(&path, false)
+                .expect("Failed to load variable");
+            assert_eq!(value, Some(2));
+        }
+
+        #[test]
+        fn reports_path_of_value_that_failed_to_convert() {
+            #[derive(Debug, serde::Deserialize)]
+            #[allow(dead_code)]
+            struct MyDict {
+                list: Vec<u8>,
+                quoted: u8,
+            }
+
+            let path = make_test_vimrc();
+
+            let var = VimVar::new($cmd, Scope::Global, "my_dict_var");
+            let err = var
+                .load_typed_with_config::<_, MyDict>(&path, false)
+                .unwrap_err();
+            assert!(
+                err.to_string().starts_with("g:my_dict_var.quoted: invalid type"),
+                "Unexpected error: {}",
+                err
+            );
+
+            let err = Editor::new($cmd, &path)
+                .eval::<Vec<bool>>("g:my_dict_var.list")
+                .unwrap_err();
+            assert!(
+                err.to_string().starts_with("g:my_dict_var.list[0]: invalid type"),
+                "Unexpected error: {}",
+                err
+            );
+        }
+
+        #[test]
+        fn can_eval_expression() {
+            let path = make_test_vimrc();
+
+            let value: Vec<u32> = Editor::new($cmd, &path)
+                .eval("g:my_dict_var.list[1:]")
+                .expect("Failed to eval expression");
+
+            assert_eq!(value, vec![2, 3]);
+        }
+
+        #[test]
+        fn can_eval_expression_containing_quotes() {
+            let path = make_test_vimrc();
+
+            let value: String = Editor::new($cmd, &path)
+                .eval(r#"g:my_dict_var.quoted . ' and ''there'' | "too"'"#)
+                .expect("Failed to eval expression");
+
+            assert_eq!(value, r#"it's "here" and 'there' | "too""#);
+        }
+
+        #[test]
+        fn reports_error_when_eval_expression_throws() {
+            let path = make_test_vimrc();
+
+            let result = Editor::new($cmd, &path).eval::<Value>("g:my_missing_var");
+
+            assert!(result.is_err(), "Unexpectedly succeeded: {:?}", result);
+        }
+
+        #[test]
+        fn can_call_function_with_serialized_arguments() {
+            let path = make_test_vimrc();
+
+            let mut dict = std::collections::BTreeMap::new();
+            dict.insert("key with 'quotes'", vec![1.5, -2.0]);
+            let args = ("it's \"here\"\n", 42, true, None::<u8>, dict);
+
+            let value: Value = Editor::new($cmd, &path)
+                .call("MyEcho", &args)
+                .expect("Failed to call function");
+
+            assert_eq!(
+                value,
+                json!([
+                    "it's \"here\"\n",
+                    42,
+                    true,
+                    null,
+                    { "key with 'quotes'": [1.5, -2.0] }
+                ])
+            );
+        }
+
+        #[test]
+        fn can_call_function_without_arguments() {
+            let path = make_test_vimrc();
+
+            let value: Vec<Value> = Editor::new($cmd, &path)
+                .call("MyEcho", ())
+                .expect("Failed to call function");
+
+            assert!(value.is_empty());
+        }
+
+        #[test]
+        fn can_call_function_with_single_argument() {
+            let path = make_test_vimrc();
+            let editor = Editor::new($cmd, &path);
+
+            let value: Vec<Value> = editor
+                .call("MyEcho", (None::<u8>,))
+                .expect("Failed to call function");
+            assert_eq!(value, vec![Value::Null]);
+
+            let len: usize = editor
+                .call("len", (vec![1, 2, 3],))
+                .expect("Failed to call function");
+            assert_eq!(len, 3);
+        }
+
+        #[test]
+        fn reports_error_when_calling_missing_function() {
+            let path = make_test_vimrc();
+
+            let result = Editor::new($cmd, &path).call::<Value>("MyMissingFunc", (1,));
+
+            assert!(result.is_err(), "Unexpectedly succeeded: {:?}", result);
+        }
+
+        #[test]
+        fn can_capture_command_output() {
+            let path = make_test_vimrc();
+
+            let lines = Editor::new($cmd, &path)
+                .capture("echo 'first' | echo '' | echo 'it''s third'")
+                .expect("Failed to capture output");
+
+            assert_eq!(lines, vec!["first", "", "it's third"]);
+        }
+
+        #[test]
+        fn can_capture_option_output() {
+            let path = make_test_vimrc();
+
+            let lines = Editor::new($cmd, &path)
+                .capture("set textwidth=42 | set textwidth?")
+                .expect("Failed to capture output");
+
+            assert_eq!(lines, vec!["  textwidth=42"]);
+        }
+
+        #[test]
+        fn reports_error_when_capturing_invalid_command() {
+            let path = make_test_vimrc();
+
+            let result = Editor::new($cmd, &path).capture("notacommand");
+
+            assert!(result.is_err(), "Unexpectedly succeeded: {:?}", result);
+        }
+
+        #[test]
+        fn can_eval_despite_noise_printed_by_config() {
+            let path = make_vimrc(NOISY_VIMRC);
+
+            let evaluated = Editor::new($cmd, &path)
+                .eval_with_diagnostics::<u32>("g:my_noisy_var")
+                .expect("Failed to eval expression");
+
+            assert_eq!(evaluated.value, 42);
+            assert!(
+                evaluated
+                    .diagnostics
+                    .iter()
+                    .any(|line| line.contains("noise from a plugin")),
+                "Missing noise from diagnostics: {:?}",
+                evaluated.diagnostics
+            );
+        }
+
+        #[test]
+        fn can_load_variable_despite_noise_printed_by_config() {
+            let path = make_vimrc(NOISY_VIMRC);
+
+            let var = VimVar::new($cmd, Scope::Global, "my_noisy_var");
+            let value = var
+                .load_with_config(path, false)
+                .expect("Failed to load variable");
+
+            assert_eq!(value, Some(json!(42)));
+        }
+
+        #[test]
+        fn can_eval_large_values() {
+            let path = make_test_vimrc();
+
+            let value: Vec<String> = Editor::new($cmd, &path)
+                .eval("map(range(20000), 'repeat(\"x\", 100) . v:val')")
+                .expect("Failed to eval expression");
+
+            assert_eq!(value.len(), 20000);
+            assert_eq!(value[19999], format!("{}19999", "x".repeat(100)));
+        }
+
+        #[test]
+        fn reports_error_when_result_exceeds_max_output_size() {
+            let path = make_test_vimrc();
+
+            let result = Editor::new($cmd, &path)
+                .with_max_output_size(Some(100))
+                .eval::<Value>("range(1000)");
+
+            assert!(result.is_err(), "Unexpectedly succeeded: {:?}", result);
+        }
+
+        #[test]
+        fn can_eval_raw_bytes_of_strings_that_are_not_utf8() {
+            let path = make_vimrc("let g:my_latin1_var = \"caf\\xe9 \\x80\\xff\"\n");
+
+            let raw = Editor::new($cmd, &path)
+                .eval_raw("g:my_latin1_var")
+                .expect("Failed to eval expression");
+
+            assert_eq!(raw.as_bytes(), b"caf\xe9 \x80\xff");
+        }
+
+        #[test]
+        fn can_eval_bytes_of_blob() {
+            let path = make_vimrc("let g:my_blob_var = 0z00FF10\n");
+
+            let bytes = Editor::new($cmd, &path)
+                .eval_bytes("g:my_blob_var")
+                .expect("Failed to eval expression");
+
+            assert_eq!(bytes, vec![0x00, 0xff, 0x10]);
+        }
+
+        #[test]
+        fn reports_error_when_eval_raw_of_non_string() {
+            let path = make_test_vimrc();
+
+            let result = Editor::new($cmd, &path).eval_raw("[1, 2]");
+
+            assert!(result.is_err(), "Unexpectedly succeeded: {:?}", result);
+        }
+
+        #[test]
+        fn can_eval_value_preserving_vim_types() {
+            let path = make_test_vimrc();
+
+            let value = Editor::new($cmd, &path)
+                .eval_value("[1, 1.0, 'one', v:true, v:null, 0z01FF, function('MyEcho'), {'a': [2.5]}]")
+                .expect("Failed to eval value");
+
+            let mut dict = std::collections::BTreeMap::new();
+            dict.insert("a".to_string(), VimValue::List(vec![VimValue::Float(2.5)]));
+            assert_eq!(
+                value,
+                VimValue::List(vec![
+                    VimValue::Number(1),
+                    VimValue::Float(1.0),
+                    VimValue::String("one".to_string()),
+                    VimValue::Bool(true),
+                    VimValue::Null,
+                    VimValue::Blob(vec![0x01, 0xff]),
+                    VimValue::Funcref("MyEcho".to_string()),
+                    VimValue::Dict(dict),
+                ])
+            );
+        }
+
+        #[test]
+        fn can_eval_value_of_non_finite_floats() {
+            let path = make_test_vimrc();
+
+            let value = Editor::new($cmd, &path)
+                .eval_value("[1.0 / 0, -1.0 / 0]")
+                .expect("Failed to eval value");
+
+            assert_eq!(
+                value,
+                VimValue::List(vec![
+                    VimValue::Float(f64::INFINITY),
+                    VimValue::Float(f64::NEG_INFINITY),
+                ])
+            );
+        }
+
+        #[test]
+        fn can_eval_values_that_json_encode_rejects() {
+            #[derive(Debug, serde::Deserialize)]
+            struct Config {
+                width: u32,
+                callback: Unencodable,
+                handlers: Vec<Unencodable>,
+                limit: Unencodable,
+            }
+
+            let path = make_test_vimrc();
+
+            let config: Config = Editor::new($cmd, &path)
+                .eval(concat!(
+                    "{'width': 80, 'callback': function('MyEcho'), ",
+                    "'handlers': [{-> 1}], 'limit': 1.0 / 0}"
+                ))
+                .expect("Failed to eval expression");
+
+            assert_eq!(config.width, 80);
+            assert_eq!(config.callback.kind, VimType::Funcref);
+            assert_eq!(config.callback.repr, "function('MyEcho')");
+            assert_eq!(config.handlers.len(), 1);
+            assert_eq!(config.handlers[0].kind, VimType::Funcref);
+            assert!(config.handlers[0].repr.contains("<lambda>"));
+            assert_eq!(config.limit.kind, VimType::Float);
+            assert_eq!(config.limit.repr, "inf");
+        }
+
+        #[test]
+        fn can_load_variable_as_vim_value() {
+            let path = make_test_vimrc();
+
+            let var = VimVar::new($cmd, Scope::Global, "my_global_var");
+            let value = var
+                .load_value_with_config(path, false)
+                .expect("Failed to load variable");
+
+            assert_eq!(value, Some(VimValue::String("some global value".to_string())));
+        }
+
+        #[test]
+        fn can_list_vars_with_prefix() {
+            let path = make_test_vimrc();
+
+            let vars = Editor::new($cmd, &path)
+                .list_vars(Scope::Global, Some("my_"))
+                .expect("Failed to list vars");
+
+            let vars: Vec<(&str, VimType)> =
+                vars.iter().map(|x| (x.name.as_str(), x.kind)).collect();
+            assert_eq!(
+                vars,
+                vec![
+                    ("my_dict_var", VimType::Dict),
+                    ("my_global_var", VimType::String)
+                ]
+            );
+        }
+
+        #[test]
+        fn can_list_vars_with_values() {
+            let path = make_test_vimrc();
+
+            let vars = Editor::new($cmd, &path)
+                .list_vars_with_values(Scope::Buffer, None)
+                .expect("Failed to list vars");
+
+            let var = vars
+                .iter()
+                .find(|x| x.name == "my_buffer_var")
+                .expect("Missing buffer var");
+            assert_eq!(var.kind, VimType::String);
+            assert_eq!(
+                var.value,
+                Some(VimValue::String("some buffer value".to_string()))
+            );
+        }
+
+        #[test]
+        fn reports_error_when_listing_vars_of_function_scope() {
+            let path = make_test_vimrc();
+
+            let err = Editor::new($cmd, &path)
+                .list_vars(Scope::Local, None)
+                .unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        }
+
+        #[test]
+        fn can_load_prefixed_vars_into_struct() {
+            use std::collections::BTreeMap;
+
+            #[derive(Debug, PartialEq, serde::Deserialize)]
+            struct AleConfig {
+                linters: BTreeMap<String, Vec<String>>,
+                sign_error: String,
+                #[serde(default)]
+                lint_delay: Option<u32>,
+            }
+
+            let path = make_vimrc(
+                r#"
+                let g:ale_linters = {'rust': ['cargo', 'analyzer']}
+                let g:ale_sign_error = 'X'
+                let g:aleph = 'unrelated'
+                "#,
+            );
+
+            let config: AleConfig = Editor::new($cmd, &path)
+                .load_prefixed("ale_")
+                .expect("Failed to load prefixed vars");
+
+            let mut linters = BTreeMap::new();
+            linters.insert(
+                "rust".to_string(),
+                vec!["cargo".to_string(), "analyzer".to_string()],
+            );
+            assert_eq!(
+                config,
+                AleConfig {
+                    linters,
+                    sign_error: "X".to_string(),
+                    lint_delay: None,
+                }
+            );
+        }
+
+        #[test]
+        fn reports_full_name_of_prefixed_var_that_failed_to_convert() {
+            #[derive(Debug, serde::Deserialize)]
+            #[allow(dead_code)]
+            struct AleConfig {
+                lint_delay: u32,
+            }
+
+            let path = make_vimrc("let g:ale_lint_delay = 'soon'");
+
+            let err = Editor::new($cmd, &path)
+                .load_prefixed::<AleConfig>("ale_")
+                .unwrap_err();
+            assert!(
+                err.to_string().starts_with("g:ale_lint_delay: invalid type"),
+                "Unexpected error: {}",
+                err
+            );
+        }
+
+        #[test]
+        fn can_load_options_with_types() {
+            let path = make_vimrc("set expandtab shiftwidth=4 background=dark");
+            let editor = Editor::new($cmd, &path);
+
+            assert_eq!(
+                editor.option("expandtab").expect("Failed to load option"),
+                options::OptionValue::Bool(true)
+            );
+            assert_eq!(
+                editor.option("sw").expect("Failed to load option"),
+                options::OptionValue::Number(4)
+            );
+            assert_eq!(
+                editor.option("background").expect("Failed to load option"),
+                options::OptionValue::String("dark".to_string())
+            );
+        }
+
+        #[test]
+        fn can_load_local_and_global_values_of_options() {
+            let path = make_vimrc("set shiftwidth=4\nsetglobal shiftwidth=2");
+            let editor = Editor::new($cmd, &path);
+
+            let load = |scope| {
+                editor
+                    .option_with_scope("shiftwidth", scope)
+                    .expect("Failed to load option")
+            };
+            assert_eq!(
+                load(options::OptionScope::Effective),
+                options::OptionValue::Number(4)
+            );
+            assert_eq!(
+                load(options::OptionScope::Local),
+                options::OptionValue::Number(4)
+            );
+            assert_eq!(
+                load(options::OptionScope::Global),
+                options::OptionValue::Number(2)
+            );
+        }
+
+        #[test]
+        fn reports_error_when_loading_unknown_option() {
+            let path = make_test_vimrc();
+
+            let result = Editor::new($cmd, &path).option("notanoption");
+            assert!(result.is_err(), "Unexpectedly loaded {:?}", result);
+        }
+
+        #[test]
+        fn can_list_options() {
+            let path = make_vimrc("set expandtab shiftwidth=4");
+
+            let options = Editor::new($cmd, &path)
+                .list_options()
+                .expect("Failed to list options");
+
+            let find = |name: &str| {
+                options
+                    .iter()
+                    .find(|x| x.name == name)
+                    .unwrap_or_else(|| panic!("Missing option {}", name))
+            };
+            assert_eq!(find("expandtab").kind, options::OptionType::Boolean);
+            assert_eq!(find("expandtab").value, options::OptionValue::Bool(true));
+            assert_eq!(find("shiftwidth").kind, options::OptionType::Number);
+            assert_eq!(find("shiftwidth").value, options::OptionValue::Number(4));
+            assert_eq!(find("fileformat").kind, options::OptionType::String);
+            assert!(options.windows(2).all(|x| x[0].name < x[1].name));
+        }
+
+        #[test]
+        fn can_load_where_things_were_last_set() {
+            let path = make_vimrc(concat!(
+                "set shiftwidth=3\n",
+                "function! MyFunc() abort\n",
+                "  return 1\n",
+                "endfunction\n",
+                "command! MyCmd echo 'hi'\n",
+                "command! MyCmdLonger echo 'other'\n",
+                "nnoremap <leader>x :echo 1<CR>\n",
+            ));
+            let editor = Editor::new($cmd, &path);
+            let file = std::fs::canonicalize(&path).unwrap();
+            // Returns the line after checking that it is within the vimrc
+            let located = |set_in: Option<std::path::PathBuf>, line| {
+                assert_eq!(set_in.map(|x| std::fs::canonicalize(x).unwrap()), Some(file.clone()));
+                line
+            };
+
+            let option = editor.option_provenance("sw").expect("Failed to load option");
+            assert_eq!(option.value, options::OptionValue::Number(3));
+            assert_eq!(located(option.file, option.line), Some(1));
+
+            let function = editor
+                .function_provenance("MyFunc")
+                .expect("Failed to load function");
+            assert!(function.value.iter().any(|x| x.contains("return 1")));
+            assert_eq!(located(function.file, function.line), Some(2));
+
+            let command = editor
+                .command_provenance("MyCmd")
+                .expect("Failed to load command");
+            assert_eq!(command.value, "echo 'hi'");
+            assert_eq!(located(command.file, command.line), Some(5));
+
+            let mapping = editor
+                .mapping_provenance("n", "<leader>x")
+                .expect("Failed to load mapping");
+            assert_eq!(mapping.value, ":echo 1<CR>");
+            assert_eq!(located(mapping.file, mapping.line), Some(7));
+        }
+
+        #[test]
+        fn can_load_provenance_of_option_never_set() {
+            let path = make_test_vimrc();
+
+            let option = Editor::new($cmd, &path)
+                .option_provenance("textwidth")
+                .expect("Failed to load option");
+            assert_eq!(option.value, options::OptionValue::Number(0));
+            assert_eq!(option.file, None);
+            assert_eq!(option.line, None);
+        }
+
+        #[test]
+        fn reports_error_when_loading_provenance_of_missing_things() {
+            let path = make_test_vimrc();
+            let editor = Editor::new($cmd, &path);
+
+            assert!(editor.function_provenance("NotAFunction").is_err());
+            assert!(editor.command_provenance("NotACommand").is_err());
+            assert!(editor.mapping_provenance("n", "<F12>zz").is_err());
+            assert!(editor.option_provenance("notanoption").is_err());
+        }
+
+        #[test]
+        fn does_not_run_commands_within_provenance_names() {
+            let dir = tempfile::tempdir().unwrap();
+            let marker = dir.path().join("ran");
+            let path = make_vimrc(concat!(
+                "function! MyFunc() abort\n",
+                "endfunction\n",
+                "command! MyCmd echo 'hi'\n",
+                "nnoremap a\u{16}|b :echo 1<CR>\n",
+            ));
+            let editor = Editor::new($cmd, &path);
+            let inject = format!("|call writefile([], '{}')", marker.display());
+
+            assert!(editor.option_provenance(&format!("sw{}", inject)).is_err());
+            assert!(editor.function_provenance(&format!("MyFunc{}", inject)).is_err());
+            assert!(editor.command_provenance(&format!("MyCmd{}", inject)).is_err());
+            assert!(editor.mapping_provenance("n", &format!("a{}", inject)).is_err());
+            assert!(editor.mapping_provenance("!", "x").is_err());
+            assert!(editor.mapping_provenance("n|call", "x").is_err());
+            assert!(!marker.exists(), "Command within name was run");
+
+            let mapping = editor
+                .mapping_provenance("n", "a|b")
+                .expect("Failed to load mapping");
+            assert_eq!(mapping.value, ":echo 1<CR>");
+            assert_eq!(mapping.line, Some(4));
+        }
+
+        #[test]
+        fn can_load_settings_for_file() {
+            let dir = tempfile::tempdir().unwrap();
+            std::fs::create_dir_all(dir.path().join("ftplugin")).unwrap();
+            std::fs::write(
+                dir.path().join("ftplugin").join("vimvartest.vim"),
+                "setlocal shiftwidth=7 expandtab conceallevel=2\n\
+                 let b:undo_ftplugin = 'setl sw< et< cole<'\n\
+                 let b:vimvartest_callback = function('tr')\n",
+            )
+            .unwrap();
+            let file = dir.path().join("test.vvt");
+            std::fs::write(&file, "hello\n# vim: set tw=42:\n").unwrap();
+
+            // Modifies the initial buffer to check that it does not prevent
+            // the file from being opened
+            let path = make_vimrc(&format!(
+                "set rtp^={}\n\
+                 set modeline\n\
+                 filetype plugin on\n\
+                 autocmd BufRead,BufNewFile *.vvt setfiletype vimvartest\n\
+                 put ='noise'\n",
+                dir.path().to_string_lossy()
+            ));
+
+            let settings = Editor::new($cmd, &path)
+                .settings_for_file(&file)
+                .expect("Failed to load settings");
+
+            assert_eq!(settings.filetype, "vimvartest");
+            assert_eq!(
+                settings.option("shiftwidth"),
+                Some(&options::OptionValue::Number(7))
+            );
+            assert_eq!(
+                settings.option("expandtab"),
+                Some(&options::OptionValue::Bool(true))
+            );
+            assert_eq!(
+                settings.option("textwidth"),
+                Some(&options::OptionValue::Number(42))
+            );
+            assert_eq!(
+                settings.option("conceallevel"),
+                Some(&options::OptionValue::Number(2))
+            );
+            assert_eq!(
+                settings.var("undo_ftplugin"),
+                Some(&VimValue::String("setl sw< et< cole<".into()))
+            );
+            assert_eq!(
+                settings.var("vimvartest_callback"),
+                Some(&VimValue::Funcref("tr".into()))
+            );
+            assert!(
+                !dir.path().join(".test.vvt.swp").exists(),
+                "Swap file was created"
+            );
+        }
+
+        #[test]
+        fn can_detect_filetype() {
+            let dir = tempfile::tempdir().unwrap();
+            std::fs::create_dir_all(dir.path().join("ftdetect")).unwrap();
+            std::fs::write(
+                dir.path().join("ftdetect").join("vimvartest.vim"),
+                "autocmd BufRead,BufNewFile *.vvt setfiletype vimvartest\n",
+            )
+            .unwrap();
+            let script = dir.path().join("script");
+            std::fs::write(&script, "#!/usr/bin/env python3\n").unwrap();
+
+            let path = make_vimrc(&format!(
+                "set rtp^={}\nset modeline\nfiletype on\n",
+                dir.path().to_string_lossy()
+            ));
+            let editor = Editor::new($cmd, &path);
+            let detect = |path: &std::path::Path, contents| {
+                editor
+                    .detect_filetype(path, contents)
+                    .expect("Failed to detect filetype")
+            };
+
+            // Neither of these files exist, so only the name and contents
+            // can be used
+            let missing = dir.path().join("missing");
+            assert_eq!(
+                detect(&missing.join("test.vvt"), None).as_deref(),
+                Some("vimvartest")
+            );
+            assert_eq!(detect(&missing.join("test.unknownext"), None), None);
+            assert_eq!(
+                detect(&missing.join("other"), Some("#!/bin/sh\necho hi\n")).as_deref(),
+                Some("sh")
+            );
+            assert_eq!(
+                detect(&missing.join("notes.txt"), Some("some text\nvim: ft=lua\n")).as_deref(),
+                Some("lua")
+            );
+            assert!(!missing.exists(), "Directory was created");
+
+            assert_eq!(detect(&script, None).as_deref(), Some("python"));
+        }
+
+        #[test]
+        fn can_load_highlight_groups() {
+            let path = make_vimrc(concat!(
+                "set termguicolors\n",
+                "hi MyGroup guifg=#102030 guibg=LightBlue guisp=Red gui=bold,undercurl\n",
+                "hi link MyLink MyGroup\n",
+                "hi link MyNestedLink MyLink\n",
+                "hi MyInverted guifg=bg\n",
+                "hi Normal guifg=#eeeeee guibg=#111111\n",
+                "let g:terminal_ansi_colors = ['#000000', '#ff0000']\n",
+                "let g:terminal_color_1 = '#aa0000'\n",
+            ));
+
+            let palette = Editor::new($cmd, &path)
+                .highlights()
+                .expect("Failed to load highlights");
+            let group = |name: &str| {
+                palette
+                    .group(name)
+                    .cloned()
+                    .unwrap_or_else(|| panic!("Missing group {}", name))
+            };
+
+            assert!(palette.termguicolors);
+            let expected = highlight::Highlight {
+                fg: Some(highlight::Color::Rgb(0x10, 0x20, 0x30)),
+                bg: Some(highlight::Color::Rgb(0xad, 0xd8, 0xe6)),
+                sp: Some(highlight::Color::Rgb(0xff, 0, 0)),
+                attrs: highlight::Attributes {
+                    bold: true,
+                    undercurl: true,
+                    ..Default::default()
+                },
+                link: None,
+            };
+            assert_eq!(group("MyGroup"), expected);
+            assert_eq!(
+                group("MyNestedLink"),
+                highlight::Highlight {
+                    link: Some("MyLink".to_string()),
+                    ..expected
+                }
+            );
+            assert_eq!(
+                group("MyInverted").fg,
+                Some(highlight::Color::Rgb(0x11, 0x11, 0x11))
+            );
+            assert_eq!(
+                palette.terminal_colors[..3],
+                [
+                    Some(highlight::Color::Rgb(0, 0, 0)),
+                    Some(highlight::Color::Rgb(0xaa, 0, 0)),
+                    None,
+                ]
+            );
+        }
+
+        #[test]
+        fn can_load_highlight_groups_of_colorscheme_without_termguicolors() {
+            let path = make_vimrc("set notermguicolors\ncolorscheme desert\n");
+
+            let palette = Editor::new($cmd, &path)
+                .highlights()
+                .expect("Failed to load highlights");
+
+            assert_eq!(palette.colorscheme.as_deref(), Some("desert"));
+            assert!(!palette.termguicolors);
+            let normal = palette.group("Normal").expect("Missing Normal");
+            assert!(
+                matches!(normal.fg, Some(highlight::Color::Indexed(_))),
+                "Unexpected {:?}",
+                normal
+            );
+        }
+
+        #[test]
+        fn can_export_colorscheme_as_theme() {
+            let path = make_vimrc(concat!(
+                "set termguicolors\n",
+                "colorscheme desert\n",
+                "hi Normal guifg=#eeeeee guibg=#111111\n",
+            ));
+
+            let palette = Editor::new($cmd, &path)
+                .highlights()
+                .expect("Failed to load highlights");
+
+            let kitty = palette.export(highlight::ThemeFormat::Kitty);
+            assert!(kitty.starts_with("# desert\n"), "{}", kitty);
+            assert!(kitty.contains("\nforeground #eeeeee\n"), "{}", kitty);
+            assert!(kitty.contains("\nbackground #111111\n"), "{}", kitty);
+
+            let tmtheme = palette.export(highlight::ThemeFormat::TmTheme);
+            assert!(
+                tmtheme.contains("<key>scope</key><string>comment</string>"),
+                "{}",
+                tmtheme
+            );
+        }
+
+        #[test]
+        fn can_highlight_text() {
+            let path = make_vimrc(concat!(
+                "syntax on\n",
+                "set termguicolors\n",
+                "hi Normal guifg=#eeeeee guibg=#111111\n",
+                "hi Comment guifg=#888888 gui=italic\n",
+            ));
+
+            let spans = Editor::new($cmd, &path)
+                .highlight_text("vim", "let x = 1 \" note\n\necho 'é'\n")
+                .expect("Failed to highlight text");
+
+            // Spans cover every character of each line
+            let lines: Vec<String> = (0..3)
+                .map(|i| {
+                    spans
+                        .iter()
+                        .filter(|x| x.line == i)
+                        .map(|x| x.text.as_str())
+                        .collect()
+                })
+                .collect();
+            assert_eq!(lines, vec!["let x = 1 \" note", "", "echo 'é'"]);
+
+            let comment = spans
+                .iter()
+                .find(|x| x.text.contains("note"))
+                .expect("Missing comment");
+            assert_eq!(comment.start, 10);
+            assert_eq!(
+                comment.style.fg,
+                Some(highlight::Color::Rgb(0x88, 0x88, 0x88))
+            );
+            assert_eq!(
+                comment.style.bg,
+                Some(highlight::Color::Rgb(0x11, 0x11, 0x11))
+            );
+            assert!(comment.style.attrs.italic);
+
+            let string = spans
+                .iter()
+                .find(|x| x.text == "'é'")
+                .expect("Missing string");
+            assert_ne!(string.group, None);
+        }
+
+        #[test]
+        fn can_reindent_and_format_text() {
+            let path = make_vimrc(concat!(
+                "autocmd FileType vimvar setlocal indentexpr=(v:lnum-1)*2 textwidth=9\n",
+                "nnoremap Q ggdd\n",
+            ));
+            let editor = Editor::new($cmd, &path);
+
+            let text = editor
+                .reindent("vimvar", "a\n      b\nc\n")
+                .expect("Failed to reindent text");
+            assert_eq!(text, "a\n  b\n    c\n");
+
+            let text = editor
+                .format("vimvar", "one two three four", FormatCommand::Format)
+                .expect("Failed to format text");
+            // Wrapped lines are indented using indentexpr
+            assert_eq!(text, "one two\n  three\n    four");
+
+            let text = editor
+                .format("vimvar", "a\r\nb\r\n", FormatCommand::Normal("Q"))
+                .expect("Failed to run normal command");
+            assert_eq!(text, "b\r\n");
+
+            let text = editor
+                .format("vimvar", "a  \nb\n", FormatCommand::Ex("%s/\\s\\+$//e"))
+                .expect("Failed to run Ex command");
+            assert_eq!(text, "a\nb\n");
+        }
+
+        #[test]
+        fn can_load_runtimepath() {
+            let (dir, path) = make_test_runtime();
+
+            let rtp = Editor::new($cmd, &path)
+                .runtimepath()
+                .expect("Failed to load runtimepath");
+
+            assert_eq!(rtp.first().map(|p| p.as_path()), Some(dir.path()));
+        }
+
+        #[test]
+        fn can_find_runtime_files() {
+            let (dir, path) = make_test_runtime();
+
+            let files = Editor::new($cmd, &path)
+                .find_runtime_files("colors/vimvar_test.vim")
+                .expect("Failed to find runtime files");
+
+            assert_eq!(
+                files,
+                vec![dir.path().join("colors").join("vimvar_test.vim")]
+            );
+        }
+
+        #[test]
+        fn find_runtime_files_returns_empty_if_nothing_matches() {
+            let (_dir, path) = make_test_runtime();
+
+            let files = Editor::new($cmd, &path)
+                .find_runtime_files("colors/vimvar_missing_*.vim")
+                .expect("Failed to find runtime files");
+
+            assert!(files.is_empty());
+        }
     };
 }
 
 mod nvim {
     use super::*;
     impl_tests!(Cmd::Neovim);
+
+    #[test]
+    fn can_detect_filetype_using_lua_rules() {
+        let path = make_vimrc("lua vim.filetype.add({ extension = { vvt = 'vimvartest' } })\n");
+
+        let filetype = Editor::new(Cmd::Neovim, &path)
+            .detect_filetype("test.vvt", Some("hello"))
+            .expect("Failed to detect filetype");
+        assert_eq!(filetype.as_deref(), Some("vimvartest"));
+    }
+
+    #[test]
+    fn can_eval_lua_expression() {
+        let path = make_test_vimrc();
+
+        let value: Value = Editor::new(Cmd::Neovim, &path)
+            .eval_lua("{ name = vim.g.my_global_var, list = { 1, 2, 3 } }")
+            .expect("Failed to eval lua");
+
+        assert_eq!(
+            value,
+            json!({ "name": "some global value", "list": [1, 2, 3] })
+        );
+    }
+
+    #[test]
+    fn can_eval_lua_statements() {
+        let path = make_test_vimrc();
+
+        let value: u32 = Editor::new(Cmd::Neovim, &path)
+            .eval_lua("local x = 40\nreturn x + 2")
+            .expect("Failed to eval lua");
+
+        assert_eq!(value, 42);
+    }
+
+    #[test]
+    fn can_eval_lua_returning_nil() {
+        let path = make_test_vimrc();
+
+        let value: Value = Editor::new(Cmd::Neovim, &path)
+            .eval_lua("{ a = vim.NIL, b = nil }")
+            .expect("Failed to eval lua");
+        assert_eq!(value, json!({ "a": null }));
+
+        let value: Option<u32> = Editor::new(Cmd::Neovim, &path)
+            .eval_lua("nil")
+            .expect("Failed to eval lua");
+        assert_eq!(value, None);
+    }
+
+    #[test]
+    fn reports_error_when_eval_lua_returns_function() {
+        let path = make_test_vimrc();
+
+        let err = Editor::new(Cmd::Neovim, &path)
+            .eval_lua::<Value>("{ opts = { callback = function() end } }")
+            .unwrap_err();
+
+        assert!(
+            err.to_string().contains(r#"result["opts"]["callback"]"#),
+            "Unexpected error: {}",
+            err
+        );
+    }
 }
 
 mod vim {
     use super::*;
     impl_tests!(Cmd::Vim);
+
+    #[test]
+    fn only_sets_terminal_colors_when_loading_highlights() {
+        let path = make_vimrc("if &t_Co == 256\n  hi Normal ctermfg=12\nendif\n");
+        let editor = Editor::new(Cmd::Vim, &path);
+
+        let t_co: String = editor.eval("&t_Co").expect("Failed to eval expression");
+        assert_ne!(t_co, "256");
+
+        let palette = editor.highlights().expect("Failed to load highlights");
+        assert_eq!(
+            palette.group("Normal").and_then(|x| x.fg),
+            Some(highlight::Color::Indexed(12))
+        );
+    }
+
+    #[test]
+    fn can_eval_raw_bytes_with_latin1_encoding() {
+        let path = make_vimrc("set encoding=latin1\nlet g:my_latin1_var = \"caf\\xe9\"\n");
+
+        let raw = Editor::new(Cmd::Vim, &path)
+            .eval_raw("g:my_latin1_var")
+            .expect("Failed to eval expression");
+
+        assert_eq!(raw.as_bytes(), b"caf\xe9");
+        assert_eq!(raw.encoding(), "latin1");
+        assert_eq!(raw.to_string_lossy(), "café");
+    }
+
+    #[test]
+    fn reports_error_when_eval_lua_with_vim() {
+        let path = make_test_vimrc();
+
+        let err = Editor::new(Cmd::Vim, &path)
+            .eval_lua::<Value>("1 + 1")
+            .unwrap_err();
+
+        assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+    }
 }
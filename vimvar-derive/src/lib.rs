@@ -0,0 +1,184 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    ext::IdentExt, parse_macro_input, spanned::Spanned, Data, DeriveInput, Expr, ExprLit, Field,
+    Fields, Lit, LitStr, Token,
+};
+
+/// Derives `vimvar::VimConfig` for a struct with named fields, where each field
+/// declares where its value is loaded from using a `#[vim(...)]` attribute
+///
+/// ### Attributes
+///
+/// * `global = "name"` loads the global variable `g:name`
+/// * `option = "name"` loads the option `&name`
+/// * `lua = "expr"` loads the result of the Lua expression `expr` via
+///   `luaeval()`
+/// * `default = value` is used when the variable has not been set, where
+///   `default` alone uses the type's `Default` implementation; without a
+///   default, `Option` fields become `None` and other fields fail to load
+///
+/// Every field is loaded using a single instance of neovim/vim, and every
+/// field that fails to load is reported rather than only the first.
+#[proc_macro_derive(VimConfig, attributes(vim))]
+pub fn derive_vim_config(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Represents where a field is loaded from
+enum Source {
+    Global(LitStr),
+    Option(LitStr),
+    Lua(LitStr),
+}
+
+/// Represents the parsed `#[vim(...)]` attributes of a field
+struct FieldAttrs {
+    source: Source,
+
+    /// `None` if no default, `Some(None)` if `default` is given without a
+    /// value, and `Some(Some(expr))` if `default = expr`
+    default: Option<Option<Expr>>,
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new(
+                    input.span(),
+                    "VimConfig can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new(
+                input.span(),
+                "VimConfig can only be derived for structs",
+            ))
+        }
+    };
+
+    // Parse every field before failing so that all invalid attributes are
+    // reported at once
+    let mut parsed = Vec::new();
+    let mut error: Option<syn::Error> = None;
+    for field in fields {
+        match parse_field_attrs(field) {
+            Ok(attrs) => parsed.push((field, attrs)),
+            Err(x) => match error.as_mut() {
+                Some(error) => error.combine(x),
+                None => error = Some(x),
+            },
+        }
+    }
+    if let Some(error) = error {
+        return Err(error);
+    }
+
+    let mut sources = Vec::new();
+    let mut loads = Vec::new();
+    let mut inits = Vec::new();
+    for (field, attrs) in parsed {
+        let ident = field.ident.as_ref().expect("Named field missing ident");
+        let ty = &field.ty;
+        let name = ident.unraw().to_string();
+        let var = format_ident!("__field_{}", ident);
+
+        sources.push(match attrs.source {
+            Source::Global(x) => quote!(::vimvar::__private::ConfigSource::Global(#x)),
+            Source::Option(x) => quote!(::vimvar::__private::ConfigSource::Option(#x)),
+            Source::Lua(x) => quote!(::vimvar::__private::ConfigSource::Lua(#x)),
+        });
+
+        let default = match attrs.default {
+            None => quote!(::std::option::Option::None::<fn() -> #ty>),
+            Some(None) => quote! {
+                ::std::option::Option::Some(<#ty as ::std::default::Default>::default)
+            },
+
+            // String literals are converted so that fields such as String and
+            // PathBuf can be given a default like "text"
+            Some(Some(Expr::Lit(ExprLit {
+                lit: Lit::Str(x), ..
+            }))) => quote! {
+                ::std::option::Option::Some(|| -> #ty { ::std::convert::From::from(#x) })
+            },
+            Some(Some(x)) => quote!(::std::option::Option::Some(|| -> #ty { #x })),
+        };
+
+        loads.push(quote!(let #var = loader.field::<#ty, _>(#name, #default);));
+        inits.push(quote!(#ident: #var.expect("Field failed to load")));
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::vimvar::VimConfig for #ident #ty_generics #where_clause {
+            fn load_with(editor: &::vimvar::Editor) -> ::std::io::Result<Self> {
+                let mut loader = ::vimvar::__private::ConfigLoader::new(editor, &[#(#sources),*])?;
+                #(#loads)*
+                loader.finish()?;
+
+                ::std::result::Result::Ok(Self {
+                    #(#inits,)*
+                })
+            }
+        }
+    })
+}
+
+fn parse_field_attrs(field: &Field) -> syn::Result<FieldAttrs> {
+    let mut source = None;
+    let mut default = None;
+
+    for attr in field.attrs.iter().filter(|x| x.path().is_ident("vim")) {
+        attr.parse_nested_meta(|meta| {
+            let make_source: Option<fn(LitStr) -> Source> = if meta.path.is_ident("global") {
+                Some(Source::Global)
+            } else if meta.path.is_ident("option") {
+                Some(Source::Option)
+            } else if meta.path.is_ident("lua") {
+                Some(Source::Lua)
+            } else {
+                None
+            };
+
+            if let Some(make_source) = make_source {
+                if source.is_some() {
+                    return Err(meta.error("only one of `global`, `option` or `lua` is allowed"));
+                }
+                source = Some(make_source(meta.value()?.parse()?));
+            } else if meta.path.is_ident("default") {
+                if default.is_some() {
+                    return Err(meta.error("duplicate `default`"));
+                }
+                default = Some(if meta.input.peek(Token![=]) {
+                    Some(meta.value()?.parse()?)
+                } else {
+                    None
+                });
+            } else {
+                return Err(meta.error(
+                    "unsupported attribute, expected `global`, `option`, `lua` or `default`",
+                ));
+            }
+
+            Ok(())
+        })?;
+    }
+
+    let source = source.ok_or_else(|| {
+        syn::Error::new(
+            field.span(),
+            "missing #[vim(global = \"...\")], #[vim(option = \"...\")] or #[vim(lua = \"...\")]",
+        )
+    })?;
+
+    Ok(FieldAttrs { source, default })
+}
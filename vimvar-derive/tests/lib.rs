@@ -0,0 +1,135 @@
+use std::{io::Write, path::PathBuf};
+use tempfile::{NamedTempFile, TempPath};
+use vimvar::*;
+
+static TEST_VIMRC: &str = r#"
+set shiftwidth=4
+let g:myplugin_width = 120
+let g:myplugin_name = 'some name'
+let g:myplugin_bad = 'not a number'
+"#;
+
+fn make_test_vimrc() -> TempPath {
+    let mut file = NamedTempFile::new().expect("Failed to create temp file");
+    file.write_all(TEST_VIMRC.as_bytes())
+        .expect("Failed to write vimrc");
+    file.into_temp_path()
+}
+
+#[derive(Debug, PartialEq, VimConfig)]
+struct MyPluginConfig {
+    #[vim(global = "myplugin_width", default = 80)]
+    width: u32,
+
+    #[vim(global = "myplugin_height", default = 24)]
+    height: u32,
+
+    #[vim(global = "myplugin_name")]
+    name: String,
+
+    #[vim(global = "myplugin_path", default = "/tmp")]
+    path: PathBuf,
+
+    #[vim(global = "myplugin_tags", default)]
+    tags: Vec<String>,
+
+    #[vim(global = "myplugin_theme")]
+    theme: Option<String>,
+
+    #[vim(option = "shiftwidth")]
+    shiftwidth: u8,
+}
+
+#[derive(Debug, VimConfig)]
+#[allow(dead_code)]
+struct BrokenConfig {
+    #[vim(global = "myplugin_width")]
+    width: u32,
+
+    #[vim(global = "myplugin_bad")]
+    bad: u32,
+
+    #[vim(global = "myplugin_missing")]
+    missing: u32,
+
+    #[vim(option = "notanoption")]
+    option: u32,
+}
+
+macro_rules! impl_tests {
+    ($cmd:expr) => {
+        #[test]
+        fn can_load_config_from_globals_and_options() {
+            let path = make_test_vimrc();
+
+            let config = MyPluginConfig::load_with(&Editor::new($cmd, &path))
+                .expect("Failed to load config");
+
+            assert_eq!(
+                config,
+                MyPluginConfig {
+                    width: 120,
+                    height: 24,
+                    name: "some name".to_string(),
+                    path: PathBuf::from("/tmp"),
+                    tags: Vec::new(),
+                    theme: None,
+                    shiftwidth: 4,
+                }
+            );
+        }
+
+        #[test]
+        fn reports_every_field_that_failed_to_load() {
+            let path = make_test_vimrc();
+
+            let err = BrokenConfig::load_with(&Editor::new($cmd, &path)).unwrap_err();
+            let err = err
+                .get_ref()
+                .and_then(|x| x.downcast_ref::<ConfigError>())
+                .expect("Missing config error");
+
+            let fields: Vec<&str> = err.errors().iter().map(|x| x.field).collect();
+            assert_eq!(fields, vec!["bad", "missing", "option"]);
+        }
+    };
+}
+
+mod nvim {
+    use super::*;
+    impl_tests!(Cmd::Neovim);
+
+    #[derive(Debug, PartialEq, VimConfig)]
+    struct LuaConfig {
+        #[vim(lua = "vim.g.myplugin_width + 1")]
+        width: u32,
+
+        #[vim(lua = "vim.g.myplugin_missing")]
+        missing: Option<u32>,
+
+        #[vim(lua = "vim.g.myplugin_height", default = 24)]
+        height: u32,
+    }
+
+    #[test]
+    fn can_load_config_from_lua() {
+        let path = make_test_vimrc();
+
+        let config =
+            LuaConfig::load_with(&Editor::new(Cmd::Neovim, &path)).expect("Failed to load config");
+
+        assert_eq!(
+            config,
+            LuaConfig {
+                width: 121,
+                missing: None,
+                height: 24,
+            }
+        );
+    }
+}
+
+mod vim {
+    use super::*;
+    impl_tests!(Cmd::Vim);
+}